@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
-enum Instruction {
+pub enum Instruction {
     Nop,
     Add(i64),
 }
@@ -23,51 +23,141 @@ impl FromStr for Instruction {
     }
 }
 
+impl Instruction {
+    /// How many cycles the instruction takes to finish.
+    pub fn cycles(&self) -> usize {
+        match self {
+            Instruction::Nop => 1,
+            Instruction::Add(_) => 2,
+        }
+    }
+}
+
+pub fn parse_program(input: &str) -> Vec<Instruction> {
+    input.lines().map(|l| l.trim().parse().unwrap()).collect()
+}
+
+pub const CRT_WIDTH: usize = 40;
+
+/// What's happening during one clock cycle.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-struct State {
+pub struct Cycle {
+    /// Counting from 1, like the puzzle does.
+    pub number: usize,
+    /// The X register during the cycle. An `addx` finishing in this cycle
+    /// hasn't changed it yet.
+    pub x: i64,
+    /// Column and row of the pixel the CRT draws during the cycle.
+    pub pixel: (usize, usize),
+    /// Whether the sprite covers that pixel.
+    pub lit: bool,
+    /// The instruction being executed.
+    pub instruction: Instruction,
+    /// Which of the instruction's cycles this is, from 0.
+    pub instruction_cycle: usize,
+}
+
+/// Runs a program one clock cycle at a time, yielding the state of the CPU
+/// and CRT during each cycle until the program ends.
+#[derive(Clone, Debug)]
+pub struct Cpu<I> {
+    program: I,
     x: i64,
     cycles_executed: usize,
+    /// The instruction in flight and how many of its cycles have passed.
+    current: Option<(Instruction, usize)>,
 }
 
-fn execute(state: State, inst: Instruction) -> State {
-    match inst {
-        Instruction::Nop => State {
-            x: state.x,
-            cycles_executed: state.cycles_executed + 1,
-        },
-        Instruction::Add(i) => State {
-            x: state.x + i,
-            cycles_executed: state.cycles_executed + 2,
-        },
+impl<I: Iterator<Item = Instruction>> Cpu<I> {
+    pub fn new(program: impl IntoIterator<IntoIter = I>) -> Self {
+        Cpu {
+            program: program.into_iter(),
+            x: 1,
+            cycles_executed: 0,
+            current: None,
+        }
+    }
+
+    pub fn x(&self) -> i64 {
+        self.x
     }
 }
 
-pub mod p1 {
-    use super::*;
+impl<I: Iterator<Item = Instruction>> Iterator for Cpu<I> {
+    type Item = Cycle;
 
-    pub fn solve(input: &str) -> i64 {
-        let instructions = input
-            .lines()
-            .map(|l| l.trim().parse::<Instruction>().unwrap());
-        let mut pending_cycle_counts = vec![220, 180, 140, 100, 60, 20];
-        let mut sum = 0;
-        let mut state = State {
-            x: 1,
-            cycles_executed: 0,
+    fn next(&mut self) -> Option<Cycle> {
+        let (instruction, done) = match self.current {
+            Some(current) => current,
+            None => (self.program.next()?, 0),
         };
-        for inst in instructions {
-            let next_state = execute(state, inst);
-            let next_cycle_count = pending_cycle_counts[pending_cycle_counts.len() - 1];
-            if next_state.cycles_executed >= next_cycle_count {
-                sum += i64::try_from(next_cycle_count).unwrap() * state.x;
-                pending_cycle_counts.pop();
-                if pending_cycle_counts.is_empty() {
-                    break;
-                }
+        let column = self.cycles_executed % CRT_WIDTH;
+        let cycle = Cycle {
+            number: self.cycles_executed + 1,
+            x: self.x,
+            pixel: (column, self.cycles_executed / CRT_WIDTH),
+            lit: (column as i64 - self.x).abs() <= 1,
+            instruction,
+            instruction_cycle: done,
+        };
+        self.cycles_executed += 1;
+        if done + 1 < instruction.cycles() {
+            self.current = Some((instruction, done + 1));
+        } else {
+            self.current = None;
+            if let Instruction::Add(i) = instruction {
+                self.x += i;
             }
-            state = next_state;
         }
-        sum
+        Some(cycle)
+    }
+}
+
+#[test]
+fn test_cpu() {
+    let program = parse_program("noop\naddx 3\naddx -5");
+    let cycles: Vec<Cycle> = Cpu::new(program.iter().copied()).collect();
+    assert_eq!(cycles.len(), 5);
+    assert_eq!(
+        cycles.iter().map(|c| c.x).collect::<Vec<_>>(),
+        [1, 1, 1, 4, 4]
+    );
+    assert_eq!(
+        cycles[2],
+        Cycle {
+            number: 3,
+            x: 1,
+            pixel: (2, 0),
+            lit: true,
+            instruction: Instruction::Add(3),
+            instruction_cycle: 1,
+        }
+    );
+    assert_eq!(cycles[4].pixel, (4, 0));
+    assert!(cycles[4].lit);
+
+    let mut cpu = Cpu::new(program);
+    assert_eq!(cpu.by_ref().count(), 5);
+    assert_eq!(cpu.x(), -1);
+
+    let program = parse_program(&("noop\n".repeat(41) + "addx 5"));
+    let cycles: Vec<Cycle> = Cpu::new(program).collect();
+    assert_eq!(cycles.len(), 43);
+    assert_eq!(cycles[40].pixel, (0, 1));
+    assert!(cycles[40].lit);
+    assert_eq!(cycles[42].pixel, (2, 1));
+    assert!(cycles[42].lit);
+}
+
+pub mod p1 {
+    use super::*;
+
+    pub fn solve(input: &str) -> i64 {
+        Cpu::new(parse_program(input))
+            .filter(|c| c.number % 40 == 20)
+            .take(6)
+            .map(|c| c.number as i64 * c.x)
+            .sum()
     }
 
     #[test]
@@ -226,29 +316,24 @@ pub mod p2 {
     use super::*;
 
     pub fn solve(input: &str) -> String {
-        let mut output = String::new();
-        let instructions = input
-            .lines()
-            .map(|l| l.trim().parse::<Instruction>().unwrap());
-        let mut state = State {
-            x: 1,
-            cycles_executed: 0,
-        };
-        for inst in instructions {
-            let next_state = execute(state, inst);
-            for cycle in state.cycles_executed..next_state.cycles_executed {
-                let sprite_pos = cycle % 40;
-                if ((state.x - 1)..=(state.x + 1)).contains(&sprite_pos.try_into().unwrap()) {
-                    output.push('#');
-                } else {
-                    output.push('.');
-                }
-                if sprite_pos == 39 {
-                    output.push('\n');
-                }
+        Cpu::new(parse_program(input)).fold(String::new(), |mut output, c| {
+            output.push(if c.lit { '#' } else { '.' });
+            if c.pixel.0 == CRT_WIDTH - 1 {
+                output.push('\n');
             }
-            state = next_state;
-        }
-        output
+            output
+        })
+    }
+
+    #[test]
+    fn test_solve() {
+        let input =
+            "addx 10\n".to_string() + &"noop\n".repeat(38) + "addx -7\n" + &"noop\n".repeat(38);
+        assert_eq!(
+            solve(&input),
+            "##........###...........................
+...###..................................
+"
+        );
     }
 }
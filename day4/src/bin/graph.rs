@@ -0,0 +1,20 @@
+use std::io::Read;
+
+use day4::AssignmentGroup;
+
+/// Prints one graphviz graph per input line; pipe through `dot -Tsvg` etc.
+pub fn main() {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    for (line_no, line) in input.lines().enumerate() {
+        let group: AssignmentGroup = line.parse().unwrap();
+        let graph = group.overlap_graph();
+        eprintln!(
+            "line {}: components {:?}, redundant elves {:?}",
+            line_no + 1,
+            graph.connected_components(),
+            graph.redundant_elves()
+        );
+        print!("{}", graph.to_dot());
+    }
+}
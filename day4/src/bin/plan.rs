@@ -0,0 +1,21 @@
+use std::io::Read;
+
+use day4::ReassignmentPlan;
+
+/// Prints a plan that removes every fully redundant assignment, or with
+/// `--overlaps` every overlap. With `--rewrite`, prints the rewritten
+/// assignment file instead.
+pub fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let remove_overlaps = args.iter().any(|a| a == "--overlaps");
+    let rewrite = args.iter().any(|a| a == "--rewrite");
+
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let plan = ReassignmentPlan::new(&input, remove_overlaps);
+    if rewrite {
+        print!("{}", plan.rewritten());
+    } else {
+        print!("{}", plan.diff());
+    }
+}
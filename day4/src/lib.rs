@@ -1,7 +1,11 @@
-use std::{ops::RangeInclusive, str::FromStr};
+use std::{
+    fmt::{Display, Write},
+    ops::RangeInclusive,
+    str::FromStr,
+};
 
 #[derive(Debug, Clone)]
-struct Assignment {
+pub struct Assignment {
     pub range: RangeInclusive<u32>,
 }
 impl FromStr for Assignment {
@@ -16,6 +20,12 @@ impl FromStr for Assignment {
     }
 }
 
+impl Display for Assignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.range.start(), self.range.end())
+    }
+}
+
 #[derive(Debug, Clone)]
 struct AssignmentPair {
     pub a1: Assignment,
@@ -48,6 +58,649 @@ impl AssignmentPair {
     }
 }
 
+impl Assignment {
+    pub fn contains(&self, other: &Assignment) -> bool {
+        self.range.contains(other.range.start()) && self.range.contains(other.range.end())
+    }
+
+    pub fn overlaps(&self, other: &Assignment) -> bool {
+        self.range.start() <= other.range.end() && other.range.start() <= self.range.end()
+    }
+}
+
+/// A roster of any number of elves, each with its own assignment.
+#[derive(Debug, Clone)]
+pub struct AssignmentGroup {
+    pub assignments: Vec<Assignment>,
+}
+impl FromStr for AssignmentGroup {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let assignments = s.split(',').map(|a| a.trim().parse().unwrap()).collect();
+        Ok(AssignmentGroup { assignments })
+    }
+}
+
+impl Display for AssignmentGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, a) in self.assignments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{a}")?;
+        }
+        Ok(())
+    }
+}
+
+impl AssignmentGroup {
+    pub fn overlap_graph(&self) -> OverlapGraph {
+        let mut edges = Vec::new();
+        for (i, a1) in self.assignments.iter().enumerate() {
+            for (j, a2) in self.assignments.iter().enumerate().skip(i + 1) {
+                if a1.overlaps(a2) {
+                    edges.push((i, j));
+                }
+            }
+        }
+        OverlapGraph {
+            assignments: self.assignments.clone(),
+            edges,
+        }
+    }
+}
+
+/// Conflict graph of a group. Nodes are elves (indexes into the group) and
+/// there's an edge between every pair of elves whose assignments overlap.
+#[derive(Debug, Clone)]
+pub struct OverlapGraph {
+    assignments: Vec<Assignment>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl OverlapGraph {
+    pub fn num_elves(&self) -> usize {
+        self.assignments.len()
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Groups of elves connected by overlaps, each sorted, in order of their
+    /// lowest member.
+    pub fn connected_components(&self) -> Vec<Vec<usize>> {
+        // Union-find over elf indexes.
+        let mut parents: Vec<usize> = (0..self.num_elves()).collect();
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+        for &(a, b) in &self.edges {
+            let ra = root(&mut parents, a);
+            let rb = root(&mut parents, b);
+            parents[ra.max(rb)] = ra.min(rb);
+        }
+
+        let mut components = Vec::<Vec<usize>>::new();
+        let mut component_of_root = vec![None::<usize>; self.num_elves()];
+        for elf in 0..self.num_elves() {
+            let r = root(&mut parents, elf);
+            match component_of_root[r] {
+                Some(c) => components[c].push(elf),
+                None => {
+                    component_of_root[r] = Some(components.len());
+                    components.push(vec![elf]);
+                }
+            }
+        }
+        components
+    }
+
+    /// Elves whose whole assignment is also covered by some other single elf.
+    /// When several elves have identical assignments, the first of them is
+    /// kept and the rest are reported.
+    pub fn redundant_elves(&self) -> Vec<usize> {
+        let a = &self.assignments;
+        (0..a.len())
+            .filter(|&i| {
+                (0..a.len())
+                    .any(|j| j != i && a[j].contains(&a[i]) && (!a[i].contains(&a[j]) || j < i))
+            })
+            .collect()
+    }
+
+    /// Graphviz representation. Edges where one assignment completely
+    /// contains the other are drawn bold, and redundant elves are filled.
+    pub fn to_dot(&self) -> String {
+        let redundant = self.redundant_elves();
+        let mut dot = String::new();
+        writeln!(dot, "graph overlaps {{").unwrap();
+        for (i, a) in self.assignments.iter().enumerate() {
+            let style = if redundant.contains(&i) {
+                ", style=filled"
+            } else {
+                ""
+            };
+            writeln!(
+                dot,
+                "    e{i} [label=\"elf {i}\\n{}-{}\"{style}];",
+                a.range.start(),
+                a.range.end()
+            )
+            .unwrap();
+        }
+        for &(a, b) in &self.edges {
+            let (a1, a2) = (&self.assignments[a], &self.assignments[b]);
+            let style = if a1.contains(a2) || a2.contains(a1) {
+                " [style=bold]"
+            } else {
+                ""
+            };
+            writeln!(dot, "    e{a} -- e{b}{style};").unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[test]
+fn test_overlap_graph() {
+    let group: AssignmentGroup = "2-4,6-8,3-3,4-7,10-12,3-3".parse().unwrap();
+    let graph = group.overlap_graph();
+    assert_eq!(graph.num_elves(), 6);
+    assert_eq!(graph.edges(), &[(0, 2), (0, 3), (0, 5), (1, 3), (2, 5)]);
+    assert_eq!(
+        graph.connected_components(),
+        vec![vec![0, 1, 2, 3, 5], vec![4]]
+    );
+    assert_eq!(graph.redundant_elves(), vec![2, 5]);
+}
+
+#[test]
+fn test_overlap_graph_dot() {
+    let group: AssignmentGroup = "2-8,3-7,9-9".parse().unwrap();
+    assert_eq!(
+        group.overlap_graph().to_dot(),
+        r#"graph overlaps {
+    e0 [label="elf 0\n2-8"];
+    e1 [label="elf 1\n3-7", style=filled];
+    e2 [label="elf 2\n9-9"];
+    e0 -- e1 [style=bold];
+}
+"#
+    );
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Edit {
+    /// Remove the elf's assignment entirely; after the other edits, the
+    /// elves in `by` cover all of it.
+    Drop { elf: usize, by: Vec<usize> },
+    /// Give the elf only `to`; after the other edits, the sections cut off
+    /// are covered by the elves in `by`.
+    Shrink {
+        elf: usize,
+        by: Vec<usize>,
+        to: RangeInclusive<u32>,
+    },
+}
+
+/// "elf 1", "elves 1 and 2", "elves 1, 2 and 3".
+fn list_elves(elves: &[usize]) -> String {
+    match elves {
+        [] => "no elf".to_string(),
+        [elf] => format!("elf {elf}"),
+        [init @ .., last] => {
+            let init: Vec<String> = init.iter().map(|e| e.to_string()).collect();
+            format!("elves {} and {last}", init.join(", "))
+        }
+    }
+}
+
+impl Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Edit::Drop { elf, by } => write!(f, "drop elf {elf} (covered by {})", list_elves(by)),
+            Edit::Shrink { elf, by, to } => write!(
+                f,
+                "shrink elf {elf} to {}-{} (overlapped {})",
+                to.start(),
+                to.end(),
+                list_elves(by)
+            ),
+        }
+    }
+}
+
+/// Proposed edits for one group, and the group after applying them.
+#[derive(Debug, Clone)]
+pub struct GroupPlan {
+    pub before: AssignmentGroup,
+    pub edits: Vec<Edit>,
+    pub after: AssignmentGroup,
+}
+
+impl AssignmentGroup {
+    /// Plans the fewest edits that leave no assignment completely contained
+    /// in another, and if `remove_overlaps` is set, no two assignments
+    /// sharing a section. The union of covered sections is unchanged.
+    ///
+    /// Every elf that isn't edited keeps its whole assignment, so those
+    /// elves must not conflict with each other. The sections none of them
+    /// cover are handed out to the other elves, each of which can take part
+    /// of only one run of such sections, since an assignment has to stay a
+    /// single range. The largest set of unedited elves for which that works
+    /// is found by trying every compatible set, which is exponential in the
+    /// size of the group but fine for the handful of elves on a line.
+    pub fn plan_reassignment(&self, remove_overlaps: bool) -> GroupPlan {
+        let a = &self.assignments;
+        let mut order: Vec<usize> = (0..a.len()).collect();
+        order.sort_by_key(|&i| (*a[i].range.start(), std::cmp::Reverse(*a[i].range.end())));
+        let planner = Planner {
+            assignments: a,
+            order,
+            remove_overlaps,
+        };
+        let mut best = None;
+        planner.search(0, &mut Vec::new(), &mut best);
+        let new_ranges = best.unwrap().1;
+
+        // Which elves now cover any of `sections`, other than `elf`.
+        let covering = |elf: usize, sections: &[RangeInclusive<u32>]| -> Vec<usize> {
+            (0..a.len())
+                .filter(|&j| j != elf)
+                .filter(|&j| match &new_ranges[j] {
+                    Some(r) => sections.iter().any(|s| overlap(r, s)),
+                    None => false,
+                })
+                .collect()
+        };
+        let mut edits = Vec::new();
+        for (elf, (old, new)) in a.iter().zip(&new_ranges).enumerate() {
+            match new {
+                None => edits.push(Edit::Drop {
+                    elf,
+                    by: covering(elf, &[old.range.clone()]),
+                }),
+                Some(to) if *to != old.range => {
+                    let mut cut = Vec::new();
+                    if to.start() > old.range.start() {
+                        cut.push(*old.range.start()..=to.start() - 1);
+                    }
+                    if to.end() < old.range.end() {
+                        cut.push(to.end() + 1..=*old.range.end());
+                    }
+                    edits.push(Edit::Shrink {
+                        elf,
+                        by: covering(elf, &cut),
+                        to: to.clone(),
+                    });
+                }
+                Some(_) => (),
+            }
+        }
+
+        let after = AssignmentGroup {
+            assignments: new_ranges
+                .into_iter()
+                .flatten()
+                .map(|range| Assignment { range })
+                .collect(),
+        };
+        GroupPlan {
+            before: self.clone(),
+            edits,
+            after,
+        }
+    }
+}
+
+/// Whether two ranges share a section.
+fn overlap(r1: &RangeInclusive<u32>, r2: &RangeInclusive<u32>) -> bool {
+    r1.start() <= r2.end() && r2.start() <= r1.end()
+}
+
+/// Search state for `AssignmentGroup::plan_reassignment`.
+struct Planner<'a> {
+    assignments: &'a [Assignment],
+    /// Elves by start section, longest first on a tie.
+    order: Vec<usize>,
+    remove_overlaps: bool,
+}
+
+/// The new range of every elf, `None` for those dropped.
+type NewRanges = Vec<Option<RangeInclusive<u32>>>;
+
+impl Planner<'_> {
+    /// Whether elves `i` and `j` can both keep their assignments as they are.
+    fn compatible(&self, i: usize, j: usize) -> bool {
+        let (a1, a2) = (&self.assignments[i], &self.assignments[j]);
+        if self.remove_overlaps {
+            !a1.overlaps(a2)
+        } else {
+            !a1.contains(a2) && !a2.contains(a1)
+        }
+    }
+
+    /// Decides whether each elf from `order[pos]` on is kept, keeping it
+    /// where possible first, and records the largest workable `kept` in
+    /// `best` along with the new ranges.
+    fn search(&self, pos: usize, kept: &mut Vec<usize>, best: &mut Option<(usize, NewRanges)>) {
+        if let Some((most, _)) = best {
+            if kept.len() + (self.order.len() - pos) <= *most {
+                return;
+            }
+        }
+        if pos == self.order.len() {
+            if let Some(ranges) = self.fill_gaps(kept) {
+                *best = Some((kept.len(), ranges));
+            }
+            return;
+        }
+        let i = self.order[pos];
+        if kept.iter().all(|&j| self.compatible(i, j)) {
+            kept.push(i);
+            self.search(pos + 1, kept, best);
+            kept.pop();
+        }
+        self.search(pos + 1, kept, best);
+    }
+
+    /// Runs of sections covered by some elf but by none of `kept`.
+    fn gaps(&self, kept: &[usize]) -> Vec<RangeInclusive<u32>> {
+        let range = |e: &usize| &self.assignments[*e].range;
+        let mut gaps = Vec::new();
+        // Sections from `next` on haven't been looked at yet.
+        let mut next = 0;
+        let mut kept = self
+            .order
+            .iter()
+            .filter(|e| kept.contains(e))
+            .map(range)
+            .peekable();
+        for r in self.order.iter().map(range) {
+            next = next.max(*r.start());
+            while next <= *r.end() {
+                match kept.peek() {
+                    Some(k) if *k.start() <= next => {
+                        next = next.max(k.end() + 1);
+                        kept.next();
+                    }
+                    Some(k) if *k.start() <= *r.end() => {
+                        gaps.push(next..=k.start() - 1);
+                        next = *k.start();
+                    }
+                    _ => {
+                        gaps.push(next..=*r.end());
+                        next = r.end() + 1;
+                    }
+                }
+            }
+        }
+        // Sections covered by elves that merely touch end to end form one
+        // run.
+        let mut merged: Vec<RangeInclusive<u32>> = Vec::new();
+        for gap in gaps {
+            match merged.last_mut() {
+                Some(last) if *last.end() + 1 == *gap.start() => {
+                    *last = *last.start()..=*gap.end();
+                }
+                _ => merged.push(gap),
+            }
+        }
+        merged
+    }
+
+    /// Splits every gap left by `kept` among the other elves, or returns
+    /// `None` if that can't be done with each elf taking a single range.
+    fn fill_gaps(&self, kept: &[usize]) -> Option<NewRanges> {
+        let a = self.assignments;
+        let mut ranges: NewRanges = vec![None; a.len()];
+        let mut used = vec![false; a.len()];
+        for &k in kept {
+            ranges[k] = Some(a[k].range.clone());
+            used[k] = true;
+        }
+        let gaps = self.gaps(kept);
+        for (g, gap) in gaps.iter().enumerate() {
+            let (crossing, mut fill): (Vec<usize>, Vec<usize>) = (0..a.len())
+                .filter(|&e| !used[e] && overlap(&a[e].range, gap))
+                .partition(|&e| match gaps.get(g + 1) {
+                    Some(next) => a[e].range.end() >= next.start(),
+                    None => false,
+                });
+            // Elves that also reach the next gap are only used if the rest
+            // can't cover this one, and then only the one ending soonest,
+            // which covers everything from its start to the end of the gap.
+            if let Some(hole) = first_uncovered(gap, fill.iter().map(|&e| &a[e].range)) {
+                let e = crossing
+                    .into_iter()
+                    .filter(|&e| *a[e].range.start() <= hole)
+                    .min_by_key(|&e| *a[e].range.end())?;
+                fill.push(e);
+            }
+            let mut next = *gap.start();
+            while next <= *gap.end() {
+                let e = fill
+                    .iter()
+                    .copied()
+                    .filter(|&e| a[e].range.contains(&next))
+                    .max_by_key(|&e| *a[e].range.end())
+                    .unwrap();
+                let end = *a[e].range.end().min(gap.end());
+                ranges[e] = Some(next..=end);
+                used[e] = true;
+                next = end + 1;
+            }
+        }
+        Some(ranges)
+    }
+}
+
+/// The first section of `gap` that none of `ranges` covers.
+fn first_uncovered<'a>(
+    gap: &RangeInclusive<u32>,
+    ranges: impl Iterator<Item = &'a RangeInclusive<u32>>,
+) -> Option<u32> {
+    let mut ranges: Vec<_> = ranges.collect();
+    ranges.sort_by_key(|r| *r.start());
+    let mut next = *gap.start();
+    for r in ranges {
+        if *r.start() > next {
+            break;
+        }
+        next = next.max(r.end() + 1);
+    }
+    (next <= *gap.end()).then_some(next)
+}
+
+/// Edits for every line of an assignment file.
+#[derive(Debug, Clone)]
+pub struct ReassignmentPlan {
+    pub groups: Vec<GroupPlan>,
+}
+
+impl ReassignmentPlan {
+    pub fn new(input: &str, remove_overlaps: bool) -> Self {
+        let groups = input
+            .lines()
+            .map(|l| {
+                l.parse::<AssignmentGroup>()
+                    .unwrap()
+                    .plan_reassignment(remove_overlaps)
+            })
+            .collect();
+        ReassignmentPlan { groups }
+    }
+
+    /// Diff-like listing of the lines that change, with the reason for each
+    /// edit.
+    pub fn diff(&self) -> String {
+        let mut diff = String::new();
+        for (line_idx, g) in self.groups.iter().enumerate() {
+            if g.edits.is_empty() {
+                continue;
+            }
+            writeln!(diff, "@@ line {} @@", line_idx + 1).unwrap();
+            writeln!(diff, "-{}", g.before).unwrap();
+            writeln!(diff, "+{}", g.after).unwrap();
+            for e in &g.edits {
+                writeln!(diff, "#  {e}").unwrap();
+            }
+        }
+        diff
+    }
+
+    /// The assignment file with every edit applied. Dropped elves are
+    /// omitted from their line.
+    pub fn rewritten(&self) -> String {
+        let mut out = String::new();
+        for g in &self.groups {
+            writeln!(out, "{}", g.after).unwrap();
+        }
+        out
+    }
+}
+
+#[test]
+fn test_plan_reassignment() {
+    let input = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+
+    let plan = ReassignmentPlan::new(input, false);
+    assert_eq!(
+        plan.rewritten(),
+        "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8
+4-6
+2-6,4-8
+"
+    );
+    assert_eq!(
+        plan.diff(),
+        "@@ line 4 @@
+-2-8,3-7
++2-8
+#  drop elf 1 (covered by elf 0)
+@@ line 5 @@
+-6-6,4-6
++4-6
+#  drop elf 0 (covered by elf 1)
+"
+    );
+    let rewritten = plan.rewritten();
+    assert_eq!(
+        rewritten
+            .lines()
+            .filter(|l| l.contains(','))
+            .map(|l| l.parse::<AssignmentPair>().unwrap())
+            .filter(AssignmentPair::is_completely_redundant)
+            .count(),
+        0
+    );
+
+    let plan = ReassignmentPlan::new(input, true);
+    assert_eq!(
+        plan.rewritten(),
+        "2-4,6-8
+2-3,4-5
+5-7,8-9
+2-8
+4-6
+2-6,7-8
+"
+    );
+}
+
+#[test]
+fn test_plan_reassignment_group() {
+    let group: AssignmentGroup = "1-10,3-4,3-4,9-12,11-11,12-15".parse().unwrap();
+    let plan = group.plan_reassignment(false);
+    assert_eq!(
+        plan.edits,
+        vec![
+            Edit::Drop {
+                elf: 1,
+                by: vec![0]
+            },
+            Edit::Drop {
+                elf: 2,
+                by: vec![0]
+            },
+            Edit::Drop {
+                elf: 4,
+                by: vec![3]
+            },
+        ]
+    );
+    assert_eq!(plan.after.to_string(), "1-10,9-12,12-15");
+
+    let plan = group.plan_reassignment(true);
+    assert_eq!(plan.after.to_string(), "1-10,11-11,12-15");
+    assert_eq!(plan.edits.len(), 3);
+}
+
+#[test]
+fn test_plan_reassignment_fewest_edits() {
+    // Dropping elf 1 beats shrinking both elf 1 and elf 2.
+    let group: AssignmentGroup = "1-5,3-8,6-10".parse().unwrap();
+    let plan = group.plan_reassignment(true);
+    assert_eq!(plan.after.to_string(), "1-5,6-10");
+    assert_eq!(
+        plan.edits,
+        vec![Edit::Drop {
+            elf: 1,
+            by: vec![0, 2]
+        }]
+    );
+    assert_eq!(
+        plan.edits[0].to_string(),
+        "drop elf 1 (covered by elves 0 and 2)"
+    );
+
+    // Cutting both ends off elf 0 beats dropping both of the others.
+    let group: AssignmentGroup = "1-10,1-2,9-10".parse().unwrap();
+    let plan = group.plan_reassignment(false);
+    assert_eq!(plan.after.to_string(), "3-8,1-2,9-10");
+    assert_eq!(
+        plan.edits[0].to_string(),
+        "shrink elf 0 to 3-8 (overlapped elves 1 and 2)"
+    );
+
+    // Elf 0 can't fill both gaps around a kept elf 1.
+    let group: AssignmentGroup = "1-10,4-6".parse().unwrap();
+    let plan = group.plan_reassignment(false);
+    assert_eq!(plan.after.to_string(), "1-10");
+}
+
+#[test]
+fn test_plan_reassignment_reasons() {
+    // Elf 0 no longer covers 8-10 of elf 2 once elf 1 takes 11-15, so
+    // both get the credit.
+    let group: AssignmentGroup = "1-10,5-15,8-12".parse().unwrap();
+    let plan = group.plan_reassignment(true);
+    assert_eq!(plan.after.to_string(), "1-10,11-15");
+    let edits: Vec<String> = plan.edits.iter().map(Edit::to_string).collect();
+    assert_eq!(
+        edits,
+        [
+            "shrink elf 1 to 11-15 (overlapped elf 0)",
+            "drop elf 2 (covered by elves 0 and 1)"
+        ]
+    );
+}
+
 pub mod p1 {
     use super::*;
 
@@ -0,0 +1,17 @@
+use std::io::Read;
+
+use day5::{reconstruct_with, CrateMover9000, CrateMover9001};
+
+/// Reads a puzzle whose diagram is the *final* arrangement and prints the
+/// puzzle input that ends there. Pass `9001` to use the CrateMover 9001.
+pub fn main() {
+    let model = std::env::args().nth(1);
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let puzzle = match model.as_deref() {
+        None | Some("9000") => reconstruct_with(&input, &mut CrateMover9000),
+        Some("9001") => reconstruct_with(&input, &mut CrateMover9001),
+        Some(m) => panic!("Unrecognized model {m}"),
+    };
+    println!("{}", puzzle.unwrap_or_else(|e| panic!("{e}")));
+}
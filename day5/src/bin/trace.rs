@@ -0,0 +1,29 @@
+use std::io::Read;
+
+use day5::{trace_with, AlternatingCrane, CappedCrane, CrateMover9000, CrateMover9001};
+
+/// Prints the crate diagram before and after every instruction. The crane
+/// model is chosen by the first argument: `9000` (default), `9001`,
+/// `capped=<capacity>`, or `alternating`.
+pub fn main() {
+    let model = std::env::args().nth(1);
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let diagrams = match model.as_deref() {
+        None | Some("9000") => trace_with(&input, &mut CrateMover9000),
+        Some("9001") => trace_with(&input, &mut CrateMover9001),
+        Some("alternating") => trace_with(&input, &mut AlternatingCrane::default()),
+        Some(m) => match m.strip_prefix("capped=") {
+            Some(capacity) => {
+                let mut crane = CappedCrane::new(capacity.parse().unwrap())
+                    .unwrap_or_else(|| panic!("A capped crane needs a capacity of at least 1"));
+                trace_with(&input, &mut crane)
+            }
+            None => panic!("Unrecognized model {m}"),
+        },
+    };
+    let diagrams = diagrams.unwrap_or_else(|e| panic!("{e}"));
+    for d in diagrams {
+        println!("{d}\n");
+    }
+}
@@ -1,38 +1,75 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
-#[derive(Debug)]
-struct Stacks {
-    stacks: Vec<Vec<char>>,
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Stacks {
+    stacks: Vec<Vec<String>>,
+}
+
+/// Each whitespace-separated word in `line`, with the column it starts at.
+fn words_with_columns(line: &str) -> Vec<(usize, String)> {
+    let mut words = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (col, c) in line.chars().enumerate() {
+        if c.is_whitespace() {
+            words.extend(current.take());
+        } else {
+            current
+                .get_or_insert_with(|| (col, String::new()))
+                .1
+                .push(c);
+        }
+    }
+    words.extend(current);
+    words
 }
 
 impl FromStr for Stacks {
     type Err = ();
 
+    /// Crates are assigned to whichever stack's number they're centered
+    /// closest to, so stacks don't need to be a fixed width apart and lines
+    /// may be ragged or trimmed. Labels can be anything without a `]`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut rev_lines = s.lines().rev();
-        let numbers_line = rev_lines.next().unwrap();
-        let num_stacks = numbers_line.trim().split_whitespace().count();
+        let numbers_line = rev_lines.next().ok_or(())?;
+
+        // Doubled so that centers of even-width spans stay integral.
+        let mut doubled_centers = Vec::new();
+        for (idx, (col, word)) in words_with_columns(numbers_line).into_iter().enumerate() {
+            if word.parse::<usize>() != Ok(idx + 1) {
+                return Err(());
+            }
+            doubled_centers.push(2 * col + word.chars().count() - 1);
+        }
 
         let mut stacks = Vec::new();
-        stacks.resize_with(num_stacks, Vec::new);
-
-        while let Some(line) = rev_lines.next() {
-            let mut chars = line.chars();
-            for stack_idx in 0..num_stacks {
-                let c = chars.next().unwrap();
-                if c == ' ' {
-                    assert_eq!(chars.next().unwrap(), ' ');
-                    assert_eq!(chars.next().unwrap(), ' ');
-                    chars.next();
-                    continue;
+        stacks.resize_with(doubled_centers.len(), Vec::new);
+
+        for line in rev_lines {
+            let chars: Vec<char> = line.chars().collect();
+            let mut filled = vec![false; stacks.len()];
+            let mut col = 0;
+            while col < chars.len() {
+                match chars[col] {
+                    c if c.is_whitespace() => col += 1,
+                    '[' => {
+                        let end = col + chars[col..].iter().position(|c| *c == ']').ok_or(())?;
+                        if end == col + 1 {
+                            return Err(());
+                        }
+                        let label: String = chars[col + 1..end].iter().collect();
+                        let stack_idx = (0..stacks.len())
+                            .min_by_key(|i| doubled_centers[*i].abs_diff(col + end))
+                            .ok_or(())?;
+                        if filled[stack_idx] {
+                            return Err(());
+                        }
+                        filled[stack_idx] = true;
+                        stacks[stack_idx].push(label);
+                        col = end + 1;
+                    }
+                    _ => return Err(()),
                 }
-                assert_eq!(c, '[');
-                let item = chars.next().unwrap();
-                assert!(item.is_alphabetic());
-                stacks[stack_idx].push(item);
-                assert_eq!(chars.next().unwrap(), ']');
-                // Eat whitespace if we're not at end
-                chars.next();
             }
         }
         Ok(Stacks { stacks })
@@ -45,35 +82,298 @@ fn test_parse_stacks() {
 [Z] [M] [P]
  1   2   3 "#;
     let stacks: Stacks = input.parse().unwrap();
-    assert_eq!(stacks.stacks[0], vec!['Z', 'N']);
-    assert_eq!(stacks.stacks[1], vec!['M', 'C', 'D']);
-    assert_eq!(stacks.stacks[2], vec!['P']);
+    assert_eq!(stacks.stacks[0], vec!["Z", "N"]);
+    assert_eq!(stacks.stacks[1], vec!["M", "C", "D"]);
+    assert_eq!(stacks.stacks[2], vec!["P"]);
+}
+
+#[test]
+fn test_parse_stacks_trimmed() {
+    let input = "    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3";
+    let stacks: Stacks = input.parse().unwrap();
+    assert_eq!(stacks.stacks[0], vec!["Z", "N"]);
+    assert_eq!(stacks.stacks[1], vec!["M", "C", "D"]);
+    assert_eq!(stacks.stacks[2], vec!["P"]);
+}
+
+#[test]
+fn test_parse_stacks_wide() {
+    let input = "                                    [J]     [L]
+[A] [B] [C] [D] [E] [F] [G] [H] [I] [K] [ 7] [M]
+ 1   2   3   4   5   6   7   8   9  10   11  12";
+    let stacks: Stacks = input.parse().unwrap();
+    assert_eq!(stacks.stacks.len(), 12);
+    assert_eq!(stacks.stacks[8], vec!["I"]);
+    assert_eq!(stacks.stacks[9], vec!["K", "J"]);
+    assert_eq!(stacks.stacks[10], vec![" 7"]);
+    assert_eq!(stacks.stacks[11], vec!["M", "L"]);
+
+    let input = "[AB]  [x]
+[C-D] [#]
+  1    2";
+    let stacks: Stacks = input.parse().unwrap();
+    assert_eq!(stacks.stacks[0], vec!["C-D", "AB"]);
+    assert_eq!(stacks.stacks[1], vec!["#", "x"]);
+    assert_eq!(stacks.message(), "ABx");
+}
+
+#[test]
+fn test_parse_stacks_invalid() {
+    assert!("[A]\n 2".parse::<Stacks>().is_err());
+    assert!("[A] [B\n 1   2".parse::<Stacks>().is_err());
+    assert!("[A]x\n 1".parse::<Stacks>().is_err());
+    assert!("[A][B]\n 1   2".parse::<Stacks>().is_ok());
+    assert!("[A] [B]\n   1".parse::<Stacks>().is_err());
+}
+
+impl Display for Stacks {
+    /// Each stack is centered under its widest label, so standard
+    /// single-letter, single-digit arrangements come out exactly as in the
+    /// puzzle input.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let widths: Vec<usize> = self
+            .stacks
+            .iter()
+            .enumerate()
+            .map(|(idx, stack)| {
+                let label_width = stack.iter().map(|l| l.chars().count() + 2).max();
+                let number_width = (idx + 1).to_string().len();
+                std::cmp::max(3, std::cmp::max(label_width.unwrap_or(0), number_width))
+            })
+            .collect();
+        let centered = |f: &mut std::fmt::Formatter<'_>, s: &str, width: usize| {
+            let pad = width - s.chars().count();
+            write!(
+                f,
+                "{:left$}{s}{:right$}",
+                "",
+                "",
+                left = pad / 2,
+                right = pad - pad / 2
+            )
+        };
+
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            for (stack_idx, stack) in self.stacks.iter().enumerate() {
+                if stack_idx > 0 {
+                    write!(f, " ")?;
+                }
+                match stack.get(level) {
+                    Some(item) => centered(f, &format!("[{item}]"), widths[stack_idx])?,
+                    None => centered(f, "", widths[stack_idx])?,
+                }
+            }
+            writeln!(f)?;
+        }
+        for (stack_idx, width) in widths.iter().enumerate() {
+            if stack_idx > 0 {
+                write!(f, " ")?;
+            }
+            centered(f, &(stack_idx + 1).to_string(), *width)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_display_stacks() {
+    let input = r#"    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 "#;
+    let stacks: Stacks = input.parse().unwrap();
+    assert_eq!(stacks.to_string(), input);
+
+    let stacks = Stacks {
+        stacks: vec![vec![], vec!["A".to_string()]],
+    };
+    assert_eq!(stacks.to_string(), "    [A]\n 1   2 ");
+
+    let stacks = Stacks {
+        stacks: vec![
+            vec!["AB".to_string(), "C".to_string()],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec!["X".to_string()],
+        ],
+    };
+    let rendered = stacks.to_string();
+    assert_eq!(
+        rendered,
+        "[C]                                     
+[AB]                                 [X]
+ 1    2   3   4   5   6   7   8   9  10 "
+    );
+    let reparsed: Stacks = rendered.parse().unwrap();
+    assert_eq!(reparsed.stacks, stacks.stacks);
 }
 
 impl Stacks {
-    pub fn execute(&mut self, instruction: &Instruction) {
-        for _ in 0..instruction.count {
-            let item = self.stacks[instruction.src - 1].pop().unwrap();
-            self.stacks[instruction.dst - 1].push(item);
+    /// Returns the arrangement after each instruction is applied by `crane`.
+    /// Stops after the first instruction that can't be carried out.
+    pub fn trace<'a>(
+        mut self,
+        instructions: &'a [Instruction],
+        crane: &'a mut impl Crane,
+    ) -> impl Iterator<Item = Result<Stacks, InstructionError>> + 'a {
+        let mut failed = false;
+        instructions.iter().map_while(move |i| {
+            if failed {
+                return None;
+            }
+            let res = self.execute(crane, i).map(|()| self.clone());
+            failed = res.is_err();
+            Some(res)
+        })
+    }
+
+    /// Checks that stacks `from` and `to` exist and that `from` holds at
+    /// least `count` crates.
+    fn check(&self, count: usize, from: usize, to: usize) -> Result<(), InstructionError> {
+        for stack in [from, to] {
+            if stack == 0 || stack > self.stacks.len() {
+                return Err(InstructionError::NoSuchStack(stack));
+            }
+        }
+        let available = self.stacks[from - 1].len();
+        if available < count {
+            return Err(InstructionError::NotEnoughCrates {
+                stack: from,
+                available,
+            });
         }
+        Ok(())
+    }
+
+    /// Checks that `i` can be carried out before handing it to `crane`, so
+    /// a crane that lifts several times isn't stopped partway.
+    pub fn execute(
+        &mut self,
+        crane: &mut impl Crane,
+        i: &Instruction,
+    ) -> Result<(), InstructionError> {
+        self.check(i.count, i.src, i.dst)?;
+        crane.execute(self, i)
+    }
+
+    /// Checks that `i` can be run backwards before handing it to `crane`.
+    pub fn undo(
+        &mut self,
+        crane: &mut impl ReversibleCrane,
+        i: &Instruction,
+    ) -> Result<(), InstructionError> {
+        self.check(i.count, i.dst, i.src)?;
+        crane.undo(self, i)
     }
 
-    pub fn execute9001(&mut self, i: &Instruction) {
-        let start_idx = self.stacks[i.src - 1].len() - i.count;
-        let items = self.stacks[i.src - 1].split_off(start_idx);
-        self.stacks[i.dst - 1].extend(items);
+    /// Moves the top `count` crates from stack `src` to stack `dst` in a
+    /// single lift. If `reversed`, they land in the opposite order, as if
+    /// moved one at a time. This is what every `Crane` is built from.
+    pub fn lift(
+        &mut self,
+        count: usize,
+        src: usize,
+        dst: usize,
+        reversed: bool,
+    ) -> Result<(), InstructionError> {
+        self.check(count, src, dst)?;
+        let start_idx = self.stacks[src - 1].len() - count;
+        let mut items = self.stacks[src - 1].split_off(start_idx);
+        if reversed {
+            items.reverse();
+        }
+        self.stacks[dst - 1].extend(items);
+        Ok(())
     }
 
+    /// The top crate of each stack, or a space for an empty stack.
     pub fn message(&self) -> String {
         let mut chars = String::new();
         for stack in &self.stacks {
-            chars.push(stack[stack.len() - 1]);
+            match stack.last() {
+                Some(label) => chars.push_str(label),
+                None => chars.push(' '),
+            }
         }
         chars
     }
 }
 
-struct Instruction {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InstructionError {
+    NoSuchStack(usize),
+    NotEnoughCrates { stack: usize, available: usize },
+}
+
+impl Display for InstructionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstructionError::NoSuchStack(stack) => write!(f, "no stack {stack}"),
+            InstructionError::NotEnoughCrates { stack, available } => {
+                write!(f, "stack {stack} only has {available} crates")
+            }
+        }
+    }
+}
+
+/// An instruction that couldn't be carried out, and the arrangement it was
+/// applied to.
+#[derive(Debug)]
+pub struct ExecuteError {
+    pub line: usize,
+    pub instruction: Instruction,
+    pub error: InstructionError,
+    pub stacks: Stacks,
+}
+
+impl Display for ExecuteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "line {}: {}: {}",
+            self.line, self.instruction, self.error
+        )?;
+        write!(f, "{}", self.stacks)
+    }
+}
+
+impl std::error::Error for ExecuteError {}
+
+#[derive(Debug)]
+pub enum ReconstructError {
+    /// An instruction couldn't be run backwards.
+    Undo(ExecuteError),
+    /// Running the instructions forward from `start` doesn't end at the
+    /// final arrangement, so the crane's `undo` doesn't match its `execute`.
+    NoRoundTrip { start: Stacks },
+}
+
+impl Display for ReconstructError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReconstructError::Undo(e) => write!(f, "{e}"),
+            ReconstructError::NoRoundTrip { start } => {
+                writeln!(f, "redoing the instructions from here doesn't round-trip:")?;
+                write!(f, "{start}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReconstructError {}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Instruction {
     pub count: usize,
     pub src: usize,
     pub dst: usize,
@@ -94,26 +394,501 @@ impl FromStr for Instruction {
     }
 }
 
-fn parse_input(input: &str) -> (Stacks, Vec<Instruction>) {
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.src, self.dst)
+    }
+}
+
+/// Returns the starting arrangement, the instructions, and the line number
+/// of the first instruction.
+fn parse_input(input: &str) -> (Stacks, Vec<Instruction>, usize) {
     let mut parts = input.split("\n\n");
     let diagram = parts.next().unwrap();
     let instructions = parts.next().unwrap();
     assert!(parts.next().is_none());
 
     let stacks = diagram.parse().unwrap();
+    let first_line = diagram.lines().count() + 2;
     let instructions = instructions.lines().map(|l| l.parse().unwrap()).collect();
-    (stacks, instructions)
+    (stacks, instructions, first_line)
+}
+
+/// A model of crane, defining how each instruction rearranges the stacks.
+pub trait Crane {
+    /// Fails if one of the crane's lifts does.
+    fn execute(
+        &mut self,
+        stacks: &mut Stacks,
+        instruction: &Instruction,
+    ) -> Result<(), InstructionError>;
+}
+
+/// A crane whose instructions can be run backwards.
+pub trait ReversibleCrane: Crane {
+    /// Restores the arrangement from before `execute` was called with
+    /// `instruction`.
+    fn undo(
+        &mut self,
+        stacks: &mut Stacks,
+        instruction: &Instruction,
+    ) -> Result<(), InstructionError>;
+}
+
+/// Moves crates one at a time.
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn execute(&mut self, stacks: &mut Stacks, i: &Instruction) -> Result<(), InstructionError> {
+        stacks.lift(i.count, i.src, i.dst, true)
+    }
+}
+
+impl ReversibleCrane for CrateMover9000 {
+    fn undo(&mut self, stacks: &mut Stacks, i: &Instruction) -> Result<(), InstructionError> {
+        stacks.lift(i.count, i.dst, i.src, true)
+    }
+}
+
+/// Moves all of an instruction's crates at once, retaining their order.
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn execute(&mut self, stacks: &mut Stacks, i: &Instruction) -> Result<(), InstructionError> {
+        stacks.lift(i.count, i.src, i.dst, false)
+    }
+}
+
+impl ReversibleCrane for CrateMover9001 {
+    fn undo(&mut self, stacks: &mut Stacks, i: &Instruction) -> Result<(), InstructionError> {
+        stacks.lift(i.count, i.dst, i.src, false)
+    }
+}
+
+/// Moves at most `capacity` crates at once, retaining their order within
+/// each lift. A capacity of 1 behaves like the CrateMover 9000.
+pub struct CappedCrane {
+    capacity: usize,
+}
+
+impl CappedCrane {
+    /// `None` for a capacity of 0, which could never move anything.
+    pub fn new(capacity: usize) -> Option<Self> {
+        (capacity > 0).then_some(CappedCrane { capacity })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl Crane for CappedCrane {
+    fn execute(&mut self, stacks: &mut Stacks, i: &Instruction) -> Result<(), InstructionError> {
+        let mut remaining = i.count;
+        while remaining > 0 {
+            let count = std::cmp::min(remaining, self.capacity);
+            stacks.lift(count, i.src, i.dst, false)?;
+            remaining -= count;
+        }
+        Ok(())
+    }
+}
+
+impl ReversibleCrane for CappedCrane {
+    fn undo(&mut self, stacks: &mut Stacks, i: &Instruction) -> Result<(), InstructionError> {
+        // The last lift was the partial one, if any.
+        let partial = i.count % self.capacity;
+        if partial > 0 {
+            stacks.lift(partial, i.dst, i.src, false)?;
+        }
+        for _ in 0..(i.count / self.capacity) {
+            stacks.lift(self.capacity, i.dst, i.src, false)?;
+        }
+        Ok(())
+    }
+}
+
+/// Moves all of an instruction's crates at once, but every other lift
+/// (starting with the second) turns them over.
+#[derive(Default)]
+pub struct AlternatingCrane {
+    lifts: usize,
+}
+
+impl Crane for AlternatingCrane {
+    fn execute(&mut self, stacks: &mut Stacks, i: &Instruction) -> Result<(), InstructionError> {
+        stacks.lift(i.count, i.src, i.dst, self.lifts % 2 == 1)?;
+        self.lifts += 1;
+        Ok(())
+    }
+}
+
+pub fn solve_with(input: &str, crane: &mut impl Crane) -> Result<String, ExecuteError> {
+    let (mut stacks, instructions, first_line) = parse_input(input);
+    for (idx, i) in instructions.iter().enumerate() {
+        if let Err(error) = stacks.execute(crane, i) {
+            return Err(ExecuteError {
+                line: first_line + idx,
+                instruction: *i,
+                error,
+                stacks,
+            });
+        }
+    }
+    Ok(stacks.message())
+}
+
+/// Rendered diagrams of the starting arrangement followed by the
+/// arrangement after each instruction.
+pub fn trace_with(input: &str, crane: &mut impl Crane) -> Result<Vec<String>, ExecuteError> {
+    let (stacks, instructions, first_line) = parse_input(input);
+    let mut diagrams = vec![stacks.to_string()];
+    let mut last = stacks.clone();
+    for (idx, res) in stacks.trace(&instructions, crane).enumerate() {
+        match res {
+            Ok(stacks) => {
+                diagrams.push(stacks.to_string());
+                last = stacks;
+            }
+            Err(error) => {
+                return Err(ExecuteError {
+                    line: first_line + idx,
+                    instruction: instructions[idx],
+                    error,
+                    stacks: last,
+                })
+            }
+        }
+    }
+    Ok(diagrams)
+}
+
+/// Takes input in the usual format, except that the diagram is the *final*
+/// arrangement, and returns the puzzle input that leads to it: the starting
+/// arrangement followed by the same instructions.
+///
+/// The result is checked by running the instructions forward again.
+pub fn reconstruct_with<C: ReversibleCrane>(
+    input: &str,
+    crane: &mut C,
+) -> Result<String, ReconstructError> {
+    let (final_stacks, instructions, first_line) = parse_input(input);
+    let mut stacks = final_stacks.clone();
+    for (idx, i) in instructions.iter().enumerate().rev() {
+        if let Err(error) = stacks.undo(crane, i) {
+            return Err(ReconstructError::Undo(ExecuteError {
+                line: first_line + idx,
+                instruction: *i,
+                error,
+                stacks,
+            }));
+        }
+    }
+    if !replays_to(&stacks, &instructions, crane, &final_stacks) {
+        return Err(ReconstructError::NoRoundTrip { start: stacks });
+    }
+
+    let mut puzzle = format!("{stacks}\n");
+    for i in &instructions {
+        puzzle.push_str(&format!("\n{i}"));
+    }
+    Ok(puzzle)
+}
+
+/// Whether running `instructions` from `initial` succeeds and ends at
+/// `expected`.
+pub fn replays_to(
+    initial: &Stacks,
+    instructions: &[Instruction],
+    crane: &mut impl Crane,
+    expected: &Stacks,
+) -> bool {
+    match initial.clone().trace(instructions, crane).last() {
+        Some(Ok(stacks)) => stacks == *expected,
+        Some(Err(_)) => false,
+        None => initial == expected,
+    }
+}
+
+#[test]
+fn test_cranes() {
+    let input = "[A]        
+[B]        
+[C]        
+[D]        
+[E]     [F]
+ 1   2   3 
+
+move 5 from 1 to 2
+move 2 from 2 to 3
+move 2 from 3 to 1";
+    assert_eq!(solve_with(input, &mut CrateMover9000).unwrap(), "ECF");
+    assert_eq!(solve_with(input, &mut CrateMover9001).unwrap(), "ACF");
+    assert_eq!(
+        solve_with(input, &mut CappedCrane::new(1).unwrap()).unwrap(),
+        "ECF"
+    );
+    assert_eq!(
+        solve_with(input, &mut CappedCrane::new(2).unwrap()).unwrap(),
+        "EDF"
+    );
+    assert_eq!(
+        solve_with(input, &mut CappedCrane::new(5).unwrap()).unwrap(),
+        "ACF"
+    );
+    assert_eq!(
+        solve_with(input, &mut AlternatingCrane::default()).unwrap(),
+        "BCF"
+    );
+}
+
+#[test]
+fn test_custom_crane() {
+    // Only uses what's public, as a crane from another crate would.
+    /// Moves crates by way of stack `via`, keeping their order.
+    struct RelayCrane {
+        via: usize,
+    }
+
+    impl Crane for RelayCrane {
+        fn execute(
+            &mut self,
+            stacks: &mut Stacks,
+            i: &Instruction,
+        ) -> Result<(), InstructionError> {
+            stacks.lift(i.count, i.src, self.via, false)?;
+            stacks.lift(i.count, self.via, i.dst, false)
+        }
+    }
+
+    let input = "[A]            
+[B]            
+[C]     [F]    
+ 1   2   3   4 
+
+move 2 from 1 to 2
+move 1 from 3 to 2";
+    assert_eq!(
+        solve_with(input, &mut RelayCrane { via: 4 }).unwrap(),
+        "CF  "
+    );
+    assert_eq!(solve_with(input, &mut CrateMover9001).unwrap(), "CF  ");
+}
+
+#[test]
+fn test_execute_errors() {
+    let diagram = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 ";
+
+    let err = solve_with(
+        &format!("{diagram}\n\nmove 1 from 2 to 1\nmove 4 from 1 to 3"),
+        &mut CrateMover9000,
+    )
+    .unwrap_err();
+    assert_eq!(err.line, 7);
+    assert_eq!(
+        err.error,
+        InstructionError::NotEnoughCrates {
+            stack: 1,
+            available: 3
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "line 7: move 4 from 1 to 3: stack 1 only has 3 crates
+[D]        
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 "
+    );
+
+    let err = solve_with(
+        &format!("{diagram}\n\nmove 1 from 4 to 1"),
+        &mut CrateMover9001,
+    )
+    .unwrap_err();
+    assert_eq!(err.line, 6);
+    assert_eq!(err.error, InstructionError::NoSuchStack(4));
+
+    let err = trace_with(
+        &format!("{diagram}\n\nmove 1 from 2 to 0"),
+        &mut CrateMover9001,
+    )
+    .unwrap_err();
+    assert_eq!(err.error, InstructionError::NoSuchStack(0));
+
+    // Cranes check every lift too, when called directly.
+    let mut stacks: Stacks = diagram.parse().unwrap();
+    let too_many = "move 4 from 1 to 3".parse().unwrap();
+    assert_eq!(
+        CrateMover9000.execute(&mut stacks, &too_many),
+        Err(InstructionError::NotEnoughCrates {
+            stack: 1,
+            available: 2
+        })
+    );
+    assert_eq!(
+        CrateMover9001.undo(&mut stacks, &"move 1 from 1 to 4".parse().unwrap()),
+        Err(InstructionError::NoSuchStack(4))
+    );
+    assert_eq!(
+        stacks.lift(1, 0, 1, false),
+        Err(InstructionError::NoSuchStack(0))
+    );
+    assert_eq!(stacks.to_string(), diagram);
+
+    assert!(CappedCrane::new(0).is_none());
+    assert_eq!(CappedCrane::new(3).unwrap().capacity(), 3);
+}
+
+#[test]
+fn test_message_empty_stack() {
+    let input = "[A]    
+[B] [C]
+ 1   2 
+
+move 1 from 2 to 1";
+    assert_eq!(solve_with(input, &mut CrateMover9000).unwrap(), "C ");
+}
+
+#[test]
+fn test_reconstruct() {
+    let initial = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 ";
+    let instructions = "move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+    let puzzle = format!("{initial}\n\n{instructions}");
+
+    let final_9000 = trace_with(&puzzle, &mut CrateMover9000).unwrap();
+    let reconstructed = reconstruct_with(
+        &format!("{}\n\n{instructions}", final_9000.last().unwrap()),
+        &mut CrateMover9000,
+    )
+    .unwrap();
+    assert_eq!(reconstructed, puzzle);
+
+    let final_9001 = trace_with(&puzzle, &mut CrateMover9001).unwrap();
+    let reconstructed = reconstruct_with(
+        &format!("{}\n\n{instructions}", final_9001.last().unwrap()),
+        &mut CrateMover9001,
+    )
+    .unwrap();
+    assert_eq!(reconstructed, puzzle);
+
+    let mut capped = CappedCrane::new(2).unwrap();
+    let final_capped = trace_with(&puzzle, &mut capped).unwrap();
+    let reconstructed = reconstruct_with(
+        &format!("{}\n\n{instructions}", final_capped.last().unwrap()),
+        &mut capped,
+    )
+    .unwrap();
+    assert_eq!(reconstructed, puzzle);
+}
+
+#[test]
+fn test_reconstruct_target_message() {
+    let target = "[A] [B]
+ 1   2";
+    let puzzle = reconstruct_with(
+        &format!("{target}\n\nmove 2 from 1 to 2\nmove 1 from 2 to 1"),
+        &mut CrateMover9000,
+    )
+    .unwrap();
+    assert_eq!(p1::solve(&puzzle), "AB");
+}
+
+#[test]
+fn test_reconstruct_error() {
+    let err = match reconstruct_with("[A] [B]\n 1   2\n\nmove 2 from 1 to 2", &mut CrateMover9001) {
+        Err(ReconstructError::Undo(err)) => err,
+        res => panic!("{res:?}"),
+    };
+    assert_eq!(err.line, 4);
+    assert_eq!(
+        err.error,
+        InstructionError::NotEnoughCrates {
+            stack: 2,
+            available: 1
+        }
+    );
+
+    /// Undoes like the CrateMover 9001 but executes like the 9000.
+    struct Mismatched;
+
+    impl Crane for Mismatched {
+        fn execute(
+            &mut self,
+            stacks: &mut Stacks,
+            i: &Instruction,
+        ) -> Result<(), InstructionError> {
+            CrateMover9000.execute(stacks, i)
+        }
+    }
+
+    impl ReversibleCrane for Mismatched {
+        fn undo(&mut self, stacks: &mut Stacks, i: &Instruction) -> Result<(), InstructionError> {
+            CrateMover9001.undo(stacks, i)
+        }
+    }
+
+    let err = reconstruct_with(
+        "    [A]\n    [B]\n 1   2\n\nmove 2 from 1 to 2",
+        &mut Mismatched,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "redoing the instructions from here doesn't round-trip:
+[A]    
+[B]    
+ 1   2 "
+    );
+}
+
+#[test]
+fn test_replays_to() {
+    let (initial, instructions, _) = parse_input(
+        "[A]    
+[B] [C]
+ 1   2 
+
+move 2 from 1 to 2",
+    );
+    let expected: Stacks = "    [A]
+    [B]
+    [C]
+ 1   2 "
+        .parse()
+        .unwrap();
+    assert!(replays_to(
+        &initial,
+        &instructions,
+        &mut CrateMover9001,
+        &expected
+    ));
+    assert!(!replays_to(
+        &initial,
+        &instructions,
+        &mut CrateMover9000,
+        &expected
+    ));
 }
 
 pub mod p1 {
     use super::*;
 
+    pub fn trace(input: &str) -> Vec<String> {
+        trace_with(input, &mut CrateMover9000).unwrap_or_else(|e| panic!("{e}"))
+    }
+
     pub fn solve(input: &str) -> String {
-        let (mut stacks, instructions) = parse_input(input);
-        for i in &instructions {
-            stacks.execute(i);
-        }
-        stacks.message()
+        solve_with(input, &mut CrateMover9000).unwrap_or_else(|e| panic!("{e}"))
     }
     #[test]
     fn test_solve() {
@@ -128,17 +903,47 @@ move 2 from 2 to 1
 move 1 from 1 to 2";
         assert_eq!(solve(input), "CMZ");
     }
+
+    #[test]
+    fn test_trace() {
+        let input = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+        let diagrams = trace(input);
+        assert_eq!(diagrams.len(), 5);
+        assert_eq!(
+            diagrams[1],
+            "[D]        
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 "
+        );
+        assert_eq!(
+            diagrams[4],
+            "        [Z]
+        [N]
+        [D]
+[C] [M] [P]
+ 1   2   3 "
+        );
+    }
 }
 
 pub mod p2 {
     use super::*;
 
+    pub fn trace(input: &str) -> Vec<String> {
+        trace_with(input, &mut CrateMover9001).unwrap_or_else(|e| panic!("{e}"))
+    }
+
     pub fn solve(input: &str) -> String {
-        let (mut stacks, instructions) = parse_input(input);
-        for i in &instructions {
-            stacks.execute9001(i);
-        }
-        stacks.message()
+        solve_with(input, &mut CrateMover9001).unwrap_or_else(|e| panic!("{e}"))
     }
     #[test]
     fn test_solve() {
@@ -153,4 +958,25 @@ move 2 from 2 to 1
 move 1 from 1 to 2";
         assert_eq!(solve(input), "MCD");
     }
+
+    #[test]
+    fn test_trace() {
+        let input = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+        assert_eq!(
+            trace(input).last().unwrap(),
+            "        [D]
+        [N]
+        [Z]
+[M] [C] [P]
+ 1   2   3 "
+        );
+    }
 }
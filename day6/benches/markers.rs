@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+use day6::bytes_read_until_unique_n;
+
+/// A stream of `len` bytes that cycles through `n - 1` letters, so that the
+/// only `n`-byte marker is the one at the very end. The run of `a`s keeps
+/// windows straddling the marker from being unique.
+fn stream(len: usize, n: usize) -> Vec<u8> {
+    let cycle_len = len - n - (n - 1);
+    let mut bytes: Vec<u8> = (b'a'..).take(n - 1).cycle().take(cycle_len).collect();
+    bytes.extend(std::iter::repeat(b'a').take(n));
+    bytes.extend((b'B'..).take(n - 1));
+    bytes
+}
+
+/// Average time to scan `input` for an `n`-byte marker. Each iteration
+/// starts one byte further in, so the calls can't be folded together.
+fn time(input: &[u8], n: usize) -> Duration {
+    const ITERATIONS: usize = 10;
+    let start = Instant::now();
+    let mut found = 0;
+    for skip in 0..ITERATIONS {
+        found += bytes_read_until_unique_n(&input[skip..], n).unwrap_or(0);
+    }
+    let elapsed = start.elapsed();
+    assert!(found <= input.len() * ITERATIONS);
+    elapsed / ITERATIONS as u32
+}
+
+pub fn main() {
+    for mb in [1, 4, 16] {
+        let len = mb << 20;
+        for n in [4, 14] {
+            let s = stream(len, n);
+            assert_eq!(bytes_read_until_unique_n(&s, n), Some(len));
+            let elapsed = time(&s, n);
+            println!(
+                "{mb:>2} MB, n={n:>2}: {elapsed:>10.3?} ({:.0} MB/s)",
+                mb as f64 / elapsed.as_secs_f64()
+            );
+
+            let missing = &s[..len - 1];
+            assert_eq!(bytes_read_until_unique_n(missing, n), None);
+            let elapsed = time(missing, n);
+            println!("{mb:>2} MB, n={n:>2}, no marker: {elapsed:>10.3?}");
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use day6::{MarkerScanner, START_OF_MESSAGE_LEN, START_OF_PACKET_LEN};
+
+/// Streams stdin through a `MarkerScanner`, printing each marker as soon as
+/// it's found. Marker lengths can be given as arguments; the default is the
+/// start-of-packet and start-of-message lengths.
+pub fn main() {
+    let mut lens: Vec<usize> = std::env::args()
+        .skip(1)
+        .map(|a| a.parse().unwrap())
+        .collect();
+    if lens.is_empty() {
+        lens = vec![START_OF_PACKET_LEN, START_OF_MESSAGE_LEN];
+    }
+    let mut scanner = MarkerScanner::new(&lens);
+    let mut stdin = std::io::stdin().lock();
+    let mut buf = [0u8; 4096];
+    while !scanner.is_done() {
+        let read = std::io::Read::read(&mut stdin, &mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        for e in scanner.feed(&buf[..read]) {
+            println!("{}-byte marker ends at {}", e.len, e.end);
+        }
+    }
+}
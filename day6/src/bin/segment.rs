@@ -0,0 +1,20 @@
+use std::io::Read;
+
+use day6::{Segmentation, START_OF_MESSAGE_LEN, START_OF_PACKET_LEN};
+
+/// Splits stdin into alternating packet and message frames and prints the
+/// offsets of each marker and payload.
+pub fn main() {
+    let mut input = Vec::new();
+    std::io::stdin().read_to_end(&mut input).unwrap();
+    let segmentation = Segmentation::new(&input, &[START_OF_PACKET_LEN, START_OF_MESSAGE_LEN]);
+    println!("preamble {:?}", segmentation.preamble);
+    for f in &segmentation.frames {
+        let kind = match f.marker.len() {
+            START_OF_PACKET_LEN => "packet",
+            START_OF_MESSAGE_LEN => "message",
+            _ => unreachable!(),
+        };
+        println!("{kind:<7} marker {:?} payload {:?}", f.marker, f.payload);
+    }
+}
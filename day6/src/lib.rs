@@ -1,23 +1,361 @@
-use std::collections::BTreeSet;
+use std::{collections::VecDeque, io::Read, ops::Range};
 
-fn bytes_read_until_unique_n(input: &str, n: usize) -> usize {
-    let mut chars = input.chars();
-    let mut idx = 0;
-    loop {
-        let next_n: BTreeSet<char> = chars.clone().take(n).collect();
-        if next_n.len() == n {
-            return idx + n;
+/// How many times each byte value appears in a window, and how many values
+/// appear more than once.
+struct WindowCounts {
+    counts: [u32; 256],
+    repeated: usize,
+}
+
+impl WindowCounts {
+    fn new() -> Self {
+        WindowCounts {
+            counts: [0; 256],
+            repeated: 0,
+        }
+    }
+
+    fn add(&mut self, b: u8) {
+        self.counts[b as usize] += 1;
+        if self.counts[b as usize] == 2 {
+            self.repeated += 1;
+        }
+    }
+
+    fn remove(&mut self, b: u8) {
+        self.counts[b as usize] -= 1;
+        if self.counts[b as usize] == 1 {
+            self.repeated -= 1;
+        }
+    }
+
+    fn all_unique(&self) -> bool {
+        self.repeated == 0
+    }
+}
+
+/// Returns the number of bytes that have to be read before the most recent
+/// `n` are all different, or `None` if that never happens.
+///
+/// Keeps a count of each byte value in the current window, and how many
+/// values appear more than once, so each byte is only looked at when it
+/// enters and leaves the window.
+pub fn bytes_read_until_unique_n(input: &[u8], n: usize) -> Option<usize> {
+    if n == 0 {
+        return Some(0);
+    }
+    let mut window = WindowCounts::new();
+    for (idx, b) in input.iter().enumerate() {
+        window.add(*b);
+        if idx >= n {
+            window.remove(input[idx - n]);
+        }
+        if idx + 1 >= n && window.all_unique() {
+            return Some(idx + 1);
         }
-        idx += 1;
-        chars.next();
     }
+    None
+}
+
+#[test]
+fn test_bytes_read_until_unique_n() {
+    assert_eq!(bytes_read_until_unique_n(b"abcd", 4), Some(4));
+    assert_eq!(bytes_read_until_unique_n(b"abc", 4), None);
+    assert_eq!(bytes_read_until_unique_n(b"aaaaaaa", 2), None);
+    assert_eq!(bytes_read_until_unique_n(b"aaaaaab", 2), Some(7));
+    assert_eq!(bytes_read_until_unique_n(b"abab", 1), Some(1));
+    assert_eq!(bytes_read_until_unique_n(b"", 0), Some(0));
+    assert_eq!(bytes_read_until_unique_n(b"", 1), None);
+    assert_eq!(bytes_read_until_unique_n(b"abcabcabcd", 4), Some(10));
+    assert_eq!(bytes_read_until_unique_n(&[0, 255, 0, 1], 3), Some(4));
+}
+
+pub const START_OF_PACKET_LEN: usize = 4;
+pub const START_OF_MESSAGE_LEN: usize = 14;
+
+/// The first marker of some length was found.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MarkerEvent {
+    /// The configured marker length.
+    pub len: usize,
+    /// Bytes read from the start of the stream through the end of the
+    /// marker.
+    pub end: usize,
+}
+
+/// Finds the first marker of each configured length in a stream that
+/// arrives in arbitrary chunks.
+pub struct MarkerScanner {
+    /// Configured lengths that haven't been found yet, with their windows.
+    pending: Vec<(usize, WindowCounts)>,
+    /// The most recent bytes, as many as the longest pending length.
+    history: VecDeque<u8>,
+    bytes_read: usize,
+}
+
+impl MarkerScanner {
+    pub fn new(lens: &[usize]) -> Self {
+        assert!(lens.iter().all(|n| *n > 0));
+        MarkerScanner {
+            pending: lens.iter().map(|n| (*n, WindowCounts::new())).collect(),
+            history: VecDeque::new(),
+            bytes_read: 0,
+        }
+    }
+
+    /// Scans for start-of-packet and start-of-message markers.
+    pub fn packets_and_messages() -> Self {
+        Self::new(&[START_OF_PACKET_LEN, START_OF_MESSAGE_LEN])
+    }
+
+    /// Bytes scanned so far. Scanning stops once every marker is found.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// Whether every configured marker has been found.
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Scans the next chunk of the stream, returning the markers that ended
+    /// within it in stream order. Markers of different lengths ending at the
+    /// same byte are returned in configured order.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<MarkerEvent> {
+        let mut events = Vec::new();
+        for b in chunk {
+            if self.is_done() {
+                break;
+            }
+            self.bytes_read += 1;
+            self.history.push_front(*b);
+            let bytes_read = self.bytes_read;
+            let history = &self.history;
+            self.pending.retain_mut(|(n, window)| {
+                window.add(*b);
+                if let Some(old) = history.get(*n) {
+                    window.remove(*old);
+                }
+                let found = bytes_read >= *n && window.all_unique();
+                if found {
+                    events.push(MarkerEvent {
+                        len: *n,
+                        end: bytes_read,
+                    });
+                }
+                !found
+            });
+            let longest = self.pending.iter().map(|(n, _)| *n).max().unwrap_or(0);
+            self.history.truncate(longest);
+        }
+        events
+    }
+
+    /// Feeds everything from `reader` until end of stream or until every
+    /// marker has been found.
+    pub fn feed_reader(&mut self, mut reader: impl Read) -> std::io::Result<Vec<MarkerEvent>> {
+        let mut events = Vec::new();
+        let mut buf = [0u8; 8192];
+        while !self.is_done() {
+            let read = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            events.extend(self.feed(&buf[..read]));
+        }
+        Ok(events)
+    }
+}
+
+#[test]
+fn test_marker_scanner_chunks() {
+    let input = b"mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+    for chunk_size in 1..=input.len() {
+        let mut scanner = MarkerScanner::packets_and_messages();
+        let mut events = Vec::new();
+        for chunk in input.chunks(chunk_size) {
+            events.extend(scanner.feed(chunk));
+        }
+        assert_eq!(
+            events,
+            vec![
+                MarkerEvent { len: 4, end: 7 },
+                MarkerEvent { len: 14, end: 19 }
+            ]
+        );
+        assert!(scanner.is_done());
+    }
+}
+
+#[test]
+fn test_marker_scanner_custom_lens() {
+    let mut scanner = MarkerScanner::new(&[3, 1, 5]);
+    assert_eq!(scanner.feed(b"aab"), vec![MarkerEvent { len: 1, end: 1 }]);
+    assert_eq!(scanner.feed(b"c"), vec![MarkerEvent { len: 3, end: 4 }]);
+    assert!(!scanner.is_done());
+    assert_eq!(scanner.feed(b"bcdd"), vec![]);
+    assert_eq!(scanner.feed(b"efg"), vec![]);
+    assert_eq!(scanner.feed(b"more"), vec![MarkerEvent { len: 5, end: 12 }]);
+    assert!(scanner.is_done());
+    assert_eq!(scanner.bytes_read(), 12);
+}
+
+#[test]
+fn test_marker_scanner_reader() {
+    /// Hands out at most 3 bytes per read.
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = std::cmp::min(3, std::cmp::min(buf.len(), self.0.len()));
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let mut scanner = MarkerScanner::packets_and_messages();
+    let events = scanner
+        .feed_reader(Trickle(b"nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"))
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![
+            MarkerEvent { len: 4, end: 10 },
+            MarkerEvent { len: 14, end: 29 }
+        ]
+    );
+
+    let mut scanner = MarkerScanner::packets_and_messages();
+    let events = scanner.feed_reader(&b"abcabc"[..]).unwrap();
+    assert_eq!(events, vec![]);
+    assert_eq!(scanner.bytes_read(), 6);
+}
+
+/// A marker and the payload that follows it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Frame {
+    /// Offsets of the marker itself. Its length is the kind of marker.
+    pub marker: Range<usize>,
+    /// Offsets of the bytes between this marker and the next one, or the end
+    /// of the stream.
+    pub payload: Range<usize>,
+}
+
+/// A stream split at its markers.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Segmentation {
+    /// Offsets of any bytes before the first marker.
+    pub preamble: Range<usize>,
+    pub frames: Vec<Frame>,
+}
+
+impl Segmentation {
+    /// Splits `input` into frames, looking for a marker of each length in
+    /// `lens` in turn and starting over from the first length after the
+    /// last. Markers don't overlap; the search for each one starts after
+    /// the previous one ends.
+    ///
+    /// With `[START_OF_PACKET_LEN, START_OF_MESSAGE_LEN]` every packet
+    /// marker is followed by a message marker, so frames alternate between
+    /// packet headers and messages.
+    pub fn new(input: &[u8], lens: &[usize]) -> Self {
+        assert!(lens.iter().all(|n| *n > 0));
+        let mut markers = Vec::new();
+        let mut from = 0;
+        for n in lens.iter().cycle() {
+            match bytes_read_until_unique_n(&input[from..], *n) {
+                Some(read) => {
+                    let end = from + read;
+                    markers.push((end - n)..end);
+                    from = end;
+                }
+                None => break,
+            }
+        }
+
+        let preamble_end = markers.first().map_or(input.len(), |m| m.start);
+        let payload_ends = markers
+            .iter()
+            .skip(1)
+            .map(|m| m.start)
+            .chain(std::iter::once(input.len()));
+        let frames = markers
+            .iter()
+            .zip(payload_ends)
+            .map(|(marker, payload_end)| Frame {
+                marker: marker.clone(),
+                payload: marker.end..payload_end,
+            })
+            .collect();
+        Segmentation {
+            preamble: 0..preamble_end,
+            frames,
+        }
+    }
+
+    /// The payload of each frame.
+    pub fn payloads<'a>(&'a self, input: &'a [u8]) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.frames.iter().map(move |f| &input[f.payload.clone()])
+    }
+}
+
+#[test]
+fn test_segmentation() {
+    let input = b"mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+    let segmentation = Segmentation::new(input, &[START_OF_PACKET_LEN, START_OF_MESSAGE_LEN]);
+    assert_eq!(
+        segmentation,
+        Segmentation {
+            preamble: 0..3,
+            frames: vec![
+                Frame {
+                    marker: 3..7,
+                    payload: 7..11,
+                },
+                Frame {
+                    marker: 11..25,
+                    payload: 25..25,
+                },
+                Frame {
+                    marker: 25..29,
+                    payload: 29..30,
+                },
+            ]
+        }
+    );
+    assert_eq!(
+        segmentation.payloads(input).collect::<Vec<_>>(),
+        vec![&b"gblj"[..], b"", b"b"]
+    );
+}
+
+#[test]
+fn test_segmentation_single_len() {
+    let input = b"aabcabcdd";
+    let segmentation = Segmentation::new(input, &[3]);
+    assert_eq!(segmentation.preamble, 0..1);
+    assert_eq!(
+        segmentation
+            .frames
+            .iter()
+            .map(|f| f.marker.clone())
+            .collect::<Vec<_>>(),
+        vec![1..4, 4..7]
+    );
+    assert_eq!(segmentation.frames[1].payload, 7..9);
+
+    let segmentation = Segmentation::new(b"aaaa", &[2]);
+    assert_eq!(segmentation.preamble, 0..4);
+    assert!(segmentation.frames.is_empty());
 }
 
 pub mod p1 {
     use crate::bytes_read_until_unique_n;
 
     pub fn solve(input: &str) -> usize {
-        bytes_read_until_unique_n(input, 4)
+        bytes_read_until_unique_n(input.as_bytes(), 4).expect("No start-of-packet marker")
     }
     #[test]
     pub fn test_solve() {
@@ -32,7 +370,7 @@ pub mod p2 {
     use crate::bytes_read_until_unique_n;
 
     pub fn solve(input: &str) -> usize {
-        bytes_read_until_unique_n(input, 14)
+        bytes_read_until_unique_n(input.as_bytes(), 14).expect("No start-of-message marker")
     }
     #[test]
     pub fn test_solve() {
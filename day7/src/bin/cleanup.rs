@@ -0,0 +1,25 @@
+use std::io::Read;
+
+use day7::{
+    cleanup::{self, CleanupConfig},
+    FileSystem,
+};
+
+/// Reads a transcript from stdin and explains which directories to delete
+/// to fit the update. Optional arguments are the disk size and the update
+/// size.
+pub fn main() {
+    let mut args = std::env::args().skip(1).map(|a| a.parse().unwrap());
+    let default = CleanupConfig::default();
+    let config = CleanupConfig {
+        disk_size: args.next().unwrap_or(default.disk_size),
+        update_size: args.next().unwrap_or(default.update_size),
+    };
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let fs = FileSystem::from_transcript(&input).unwrap_or_else(|e| panic!("{e}"));
+    match cleanup::plan(&fs, config) {
+        Some(plan) => print!("{}", plan.explain(&fs)),
+        None => println!("The update doesn't fit even on an empty disk."),
+    }
+}
@@ -0,0 +1,14 @@
+use std::io::Read;
+
+use day7::FileSystem;
+
+/// Reads a transcript from stdin and prints each directory's total size and
+/// path, largest first, like `du -h | sort -hr`. An optional argument is the
+/// deepest level below the root to list.
+pub fn main() {
+    let max_depth = std::env::args().nth(1).map(|d| d.parse().unwrap());
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let fs = FileSystem::from_transcript(&input).unwrap_or_else(|e| panic!("{e}"));
+    print!("{}", fs.render_du(max_depth));
+}
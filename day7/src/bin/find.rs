@@ -0,0 +1,71 @@
+use std::{io::Read, str::FromStr};
+
+use day7::{
+    query::{self, Cmp, Glob, Predicate, Query},
+    FileSystem,
+};
+
+/// Reads a transcript from stdin and prints the size and path of everything
+/// that matches, e.g. `find /a -type f -size +1M -name '*.txt'`.
+///
+/// Takes an optional starting path (default `/`) followed by any of
+/// `-type f|d`, `-size N`, `-direct-size N`, `-mindepth N`, `-maxdepth N`,
+/// and `-name GLOB`. Sizes are compared like `find -size`: `+N`, `-N`, or
+/// `N`, with an optional `K`, `M`, or `G`. `-largest` prints the largest
+/// file directly in each matching directory instead.
+pub fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    let start = match args.peek() {
+        Some(arg) if !arg.starts_with('-') => args.next().unwrap(),
+        _ => "/".to_string(),
+    };
+    let mut predicates = Vec::new();
+    let mut largest = false;
+    while let Some(arg) = args.next() {
+        if arg == "-largest" {
+            largest = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .unwrap_or_else(|| panic!("{arg} needs an argument"));
+        let predicate = match arg.as_str() {
+            "-type" => Predicate::Type(parse(&arg, &value)),
+            "-size" => Predicate::Size(parse(&arg, &value)),
+            "-direct-size" => Predicate::DirectSize(parse(&arg, &value)),
+            "-maxdepth" => Predicate::Depth(Cmp::Less(parse::<usize>(&arg, &value) + 1)),
+            "-mindepth" => match parse::<usize>(&arg, &value) {
+                0 => continue,
+                n => Predicate::Depth(Cmp::More(n - 1)),
+            },
+            "-name" => Predicate::Name(Glob::new(&value)),
+            _ => panic!("Unknown option {arg}"),
+        };
+        predicates.push(predicate);
+    }
+
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let fs = FileSystem::from_transcript(&input).unwrap_or_else(|e| panic!("{e}"));
+    let start = fs
+        .lookup(&start)
+        .unwrap_or_else(|| panic!("{start} doesn't exist"));
+    let sizes = fs.sizes();
+    for id in (Query { start, predicates }).run(&fs) {
+        let id = if largest {
+            match query::largest_file(&fs, id) {
+                Some(file) => file,
+                None => continue,
+            }
+        } else {
+            id
+        };
+        println!("{}\t{}", sizes[id], fs.path(id));
+    }
+}
+
+fn parse<T: FromStr>(arg: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("Bad argument for {arg}: {value}"))
+}
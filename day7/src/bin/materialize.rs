@@ -0,0 +1,30 @@
+use std::{io::Read, path::PathBuf};
+
+use day7::{localfs, FileSystem};
+
+/// Recreates the filesystem described by the transcript on stdin as sparse
+/// files under the given directory (or a new temp directory), then checks
+/// the model's directory sizes against `du`.
+pub fn main() {
+    let dest: PathBuf = match std::env::args().nth(1) {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+        None => localfs::make_temp_dir("day7").unwrap(),
+    };
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let fs = FileSystem::from_transcript(&input).unwrap_or_else(|e| panic!("{e}"));
+    localfs::materialize(&fs, &dest).unwrap();
+    println!("{}", dest.display());
+
+    let mismatches = localfs::du_mismatches(&fs, &dest).unwrap();
+    for m in &mismatches {
+        println!("{}: expected {}, du says {}", m.path, m.expected, m.du);
+    }
+    if !mismatches.is_empty() {
+        std::process::exit(1);
+    }
+}
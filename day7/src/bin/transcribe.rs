@@ -0,0 +1,10 @@
+use std::path::PathBuf;
+
+/// Prints a transcript of exploring the given local directory.
+pub fn main() {
+    let dir: PathBuf = std::env::args()
+        .nth(1)
+        .expect("Usage: transcribe <dir>")
+        .into();
+    print!("{}", day7::localfs::transcribe(&dir).unwrap());
+}
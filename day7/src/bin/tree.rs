@@ -0,0 +1,13 @@
+use std::io::Read;
+
+use day7::FileSystem;
+
+/// Reads a transcript from stdin and prints the reconstructed filesystem.
+/// An optional argument limits how many levels below the root are shown.
+pub fn main() {
+    let max_depth = std::env::args().nth(1).map(|d| d.parse().unwrap());
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let fs = FileSystem::from_transcript(&input).unwrap_or_else(|e| panic!("{e}"));
+    print!("{}", fs.render_tree(max_depth));
+}
@@ -0,0 +1,570 @@
+//! Choosing directories to delete to make room for an update.
+
+use std::{cmp::Reverse, fmt::Write};
+
+use crate::{FileSystem, NodeId};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CleanupConfig {
+    pub disk_size: usize,
+    pub update_size: usize,
+}
+
+impl Default for CleanupConfig {
+    /// The device from the puzzle.
+    fn default() -> Self {
+        CleanupConfig {
+            disk_size: 70_000_000,
+            update_size: 30_000_000,
+        }
+    }
+}
+
+impl CleanupConfig {
+    /// How much has to be deleted before the update fits. Zero if it
+    /// already does.
+    pub fn needed(&self, used: usize) -> usize {
+        let free = self.disk_size.saturating_sub(used);
+        self.update_size.saturating_sub(free)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CleanupPlan {
+    pub config: CleanupConfig,
+    pub used: usize,
+    pub needed: usize,
+    /// Directories to delete, none inside another.
+    pub delete: Vec<NodeId>,
+    pub freed: usize,
+    /// The smallest directory that frees enough on its own, if any is
+    /// needed.
+    pub best_single: Option<NodeId>,
+    /// Whether every combination of directories was considered. If not,
+    /// `delete` is just `best_single`, or every directory directly under the
+    /// root if there's no such directory.
+    pub exhaustive: bool,
+}
+
+/// Finds the set of directories, none inside another, with the smallest
+/// total size that still frees enough space for the update. Of the sets that
+/// free the same amount, the one with the fewest directories wins, and then
+/// the one with the deepest. The root and empty directories are never
+/// chosen. Returns `None` if even deleting everything wouldn't be enough.
+///
+/// This is a subset-sum over the directory tree. Walking the directories in
+/// pre-order, row `p` of the table is a bitset of the totals that can be
+/// freed using only directories from position `p` on: either skip the
+/// directory at `p` (row `p + 1`), or delete it and skip past everything
+/// under it. Totals above the best single directory can't be optimal, so
+/// the rows only need to be that wide. To count directories, each row is
+/// split into bitsets of the totals reachable with at most so many. Only
+/// the rows still needed are kept, about one per level of nesting, and the
+/// table is rebuilt to pick each directory rather than stored whole. If even
+/// that would take too much memory, the best single directory is used
+/// instead.
+pub fn plan(fs: &FileSystem, config: CleanupConfig) -> Option<CleanupPlan> {
+    let sizes = fs.sizes();
+    let used = sizes[FileSystem::ROOT];
+    let needed = config.needed(used);
+    let make_plan = |mut delete: Vec<NodeId>, best_single, exhaustive| {
+        delete.sort_by_key(|id| fs.path(*id));
+        CleanupPlan {
+            config,
+            used,
+            needed,
+            freed: delete.iter().map(|id| sizes[*id]).sum(),
+            delete,
+            best_single,
+            exhaustive,
+        }
+    };
+    if needed == 0 {
+        return Some(make_plan(Vec::new(), None, true));
+    }
+    let top_level: Vec<NodeId> = fs
+        .children(FileSystem::ROOT)
+        .filter(|id| fs.node(*id).is_dir() && sizes[*id] > 0)
+        .collect();
+    let best_single = fs
+        .ids()
+        .filter(|id| *id != FileSystem::ROOT && fs.node(*id).is_dir() && sizes[*id] >= needed)
+        .min_by_key(|id| (sizes[*id], Reverse(fs.depth(*id))));
+    let limit = match best_single {
+        Some(id) => sizes[id],
+        None => top_level.iter().map(|id| sizes[*id]).sum(),
+    };
+    if limit < needed {
+        return None;
+    }
+    let fallback = || {
+        let delete = best_single.map_or_else(|| top_level.clone(), |id| vec![id]);
+        Some(make_plan(delete, best_single, false))
+    };
+
+    let table = Table::new(fs, &sizes, limit);
+    let fits = |layers: usize| table.peak_rows * layers * table.width() <= MAX_TABLE_WORDS;
+    if !fits(1) {
+        return fallback();
+    }
+    let reachable = table.fill(None, 0, |_, _| {}).pop().unwrap();
+    let freed = (needed..=limit).find(|t| reachable.get(*t)).unwrap();
+
+    // Allow more and more directories until `freed` can be reached.
+    let mut max_count = 1;
+    let mut count = loop {
+        if !fits(max_count + 1) {
+            return fallback();
+        }
+        let row = table.fill(Some(max_count), 0, |_, _| {});
+        if let Some(count) = (1..=max_count).find(|k| row[*k].get(freed)) {
+            break count;
+        }
+        max_count *= 2;
+    };
+
+    let mut delete = Vec::new();
+    let (mut p, mut remaining) = (0, freed);
+    while remaining > 0 {
+        // The deepest directory that can come next in a smallest set, the
+        // first in pre-order if there's a tie.
+        let mut next: Option<usize> = None;
+        table.fill(Some(count), p, |q, end| {
+            let size = table.size(q);
+            if size <= remaining
+                && end[count - 1].get(remaining - size)
+                && !matches!(next, Some(n) if table.depths[n] > table.depths[q])
+            {
+                next = Some(q);
+            }
+        });
+        let q = next.unwrap();
+        delete.push(table.order[q]);
+        remaining -= table.size(q);
+        count -= 1;
+        p = table.subtree_end[q];
+    }
+    Some(make_plan(delete, best_single, true))
+}
+
+/// Most memory the rows `plan` keeps at once may take, in 64-bit words
+/// (32 MiB).
+const MAX_TABLE_WORDS: usize = 1 << 22;
+
+/// The directories in pre-order, and what's needed to build the rows of the
+/// table in `plan` over them.
+struct Table<'a> {
+    order: Vec<NodeId>,
+    /// For each position, the position just past the directories under it.
+    subtree_end: Vec<usize>,
+    depths: Vec<usize>,
+    sizes: &'a [usize],
+    limit: usize,
+    /// For each position, the earliest row built from its row, after which
+    /// it can be dropped.
+    last_use: Vec<usize>,
+    /// The most rows kept at once while filling the table.
+    peak_rows: usize,
+}
+
+impl<'a> Table<'a> {
+    fn new(fs: &FileSystem, sizes: &'a [usize], limit: usize) -> Self {
+        let (order, subtree_end) = dirs_in_preorder(fs);
+        let depths = order.iter().map(|id| fs.depth(*id)).collect();
+        let mut table = Table {
+            order,
+            subtree_end,
+            depths,
+            sizes,
+            limit,
+            last_use: Vec::new(),
+            peak_rows: 0,
+        };
+        let n = table.order.len();
+        table.last_use = vec![usize::MAX; n + 1];
+        for p in (0..n).rev() {
+            table.last_use[p + 1] = p;
+            if table.is_candidate(p) {
+                table.last_use[table.subtree_end[p]] = p;
+            }
+        }
+        // Replay `fill` without the rows themselves: each step copies a row
+        // into a new one, then drops what's no longer needed.
+        let mut live = vec![false; n + 1];
+        let (mut rows, mut peak) = (1, 1);
+        live[n] = true;
+        for p in (0..n).rev() {
+            peak = peak.max(rows + 1);
+            for q in table.dropped_after(p) {
+                if std::mem::take(&mut live[q]) {
+                    rows -= 1;
+                }
+            }
+            rows += 1;
+            live[p] = true;
+        }
+        table.peak_rows = peak;
+        table
+    }
+
+    fn width(&self) -> usize {
+        self.limit / 64 + 1
+    }
+
+    fn size(&self, p: usize) -> usize {
+        self.sizes[self.order[p]]
+    }
+
+    /// Whether the directory at `p` may be deleted at all.
+    fn is_candidate(&self, p: usize) -> bool {
+        p > 0 && (1..=self.limit).contains(&self.size(p))
+    }
+
+    /// Rows no row before `p` is built from.
+    fn dropped_after(&self, p: usize) -> impl Iterator<Item = usize> + '_ {
+        [p + 1, self.subtree_end[p]]
+            .into_iter()
+            .filter(move |q| self.last_use[*q] == p)
+    }
+
+    /// Builds the rows from the end back to position `stop`, calling
+    /// `visit(p, row)` for each directory that may be deleted with the row
+    /// just past everything under it, and returns row `stop`. With
+    /// `max_count`, bitset `k` of each row is the totals reachable with at
+    /// most `k` directories; without it, each row is a single bitset of
+    /// every reachable total.
+    fn fill(
+        &self,
+        max_count: Option<usize>,
+        stop: usize,
+        mut visit: impl FnMut(usize, &[Bitset]),
+    ) -> Vec<Bitset> {
+        let n = self.order.len();
+        let layers = max_count.map_or(1, |k| k + 1);
+        let mut rows: Vec<Option<Vec<Bitset>>> = vec![None; n + 1];
+        let mut last = vec![Bitset::new(self.width()); layers];
+        last.iter_mut().for_each(|bits| bits.set(0));
+        rows[n] = Some(last);
+        for p in (stop..n).rev() {
+            let mut row = rows[p + 1].clone().unwrap();
+            if self.is_candidate(p) {
+                let end = rows[self.subtree_end[p]].as_ref().unwrap();
+                visit(p, end);
+                for (k, bits) in row.iter_mut().enumerate() {
+                    match max_count {
+                        Some(_) if k == 0 => {}
+                        Some(_) => bits.or_shifted(&end[k - 1], self.size(p)),
+                        None => bits.or_shifted(&end[k], self.size(p)),
+                    }
+                }
+            }
+            for q in self.dropped_after(p) {
+                rows[q] = None;
+            }
+            rows[p] = Some(row);
+        }
+        rows[stop].take().unwrap()
+    }
+}
+
+/// Directories in pre-order, and for each position the position just past
+/// the directories under it.
+fn dirs_in_preorder(fs: &FileSystem) -> (Vec<NodeId>, Vec<usize>) {
+    let mut order = Vec::new();
+    let mut subtree_end = Vec::new();
+    fn visit(fs: &FileSystem, id: NodeId, order: &mut Vec<NodeId>, subtree_end: &mut Vec<usize>) {
+        let p = order.len();
+        order.push(id);
+        subtree_end.push(0);
+        for child in fs.children(id).filter(|c| fs.node(*c).is_dir()) {
+            visit(fs, child, order, subtree_end);
+        }
+        subtree_end[p] = order.len();
+    }
+    visit(fs, FileSystem::ROOT, &mut order, &mut subtree_end);
+    (order, subtree_end)
+}
+
+#[derive(Debug, Clone)]
+struct Bitset(Vec<u64>);
+
+impl Bitset {
+    fn new(words: usize) -> Self {
+        Bitset(vec![0; words])
+    }
+
+    fn get(&self, bit: usize) -> bool {
+        self.0
+            .get(bit / 64)
+            .map_or(false, |w| w & (1 << (bit % 64)) != 0)
+    }
+
+    fn set(&mut self, bit: usize) {
+        self.0[bit / 64] |= 1 << (bit % 64);
+    }
+
+    /// Sets every bit `b + shift` where `b` is set in `other`, dropping any
+    /// that fall off the end.
+    fn or_shifted(&mut self, other: &Bitset, shift: usize) {
+        let (word_shift, bit_shift) = (shift / 64, shift % 64);
+        for i in word_shift..self.0.len() {
+            let src = i - word_shift;
+            let mut w = other.0[src] << bit_shift;
+            if bit_shift > 0 && src > 0 {
+                w |= other.0[src - 1] >> (64 - bit_shift);
+            }
+            self.0[i] |= w;
+        }
+    }
+}
+
+#[test]
+fn test_bitset() {
+    let mut a = Bitset::new(3);
+    a.set(0);
+    a.set(63);
+    a.set(140);
+    let mut b = Bitset::new(3);
+    b.or_shifted(&a, 65);
+    assert!(b.get(65));
+    assert!(b.get(128));
+    assert!(!b.get(205));
+    assert!(!b.get(64));
+    assert!(!b.get(1000));
+}
+
+impl CleanupPlan {
+    /// A human-readable account of why these directories were chosen.
+    pub fn explain(&self, fs: &FileSystem) -> String {
+        let sizes = fs.sizes();
+        let free = self.config.disk_size.saturating_sub(self.used);
+        let mut out = String::new();
+        writeln!(
+            out,
+            "{} of {} used, {free} free; the update needs {}.",
+            self.used, self.config.disk_size, self.config.update_size
+        )
+        .unwrap();
+        if self.needed == 0 {
+            writeln!(out, "Nothing needs to be deleted.").unwrap();
+            return out;
+        }
+        writeln!(out, "At least {} more must be freed.", self.needed).unwrap();
+        if !self.exhaustive {
+            let fallback = match self.best_single {
+                Some(_) => "using the smallest single directory that would do",
+                None => "deleting every directory directly under /",
+            };
+            writeln!(out, "Too many combinations to search; {fallback}.").unwrap();
+        }
+        for id in &self.delete {
+            writeln!(out, "Delete {} ({})", fs.path(*id), sizes[*id]).unwrap();
+        }
+        writeln!(
+            out,
+            "This frees {}, {} more than needed.",
+            self.freed,
+            self.freed - self.needed
+        )
+        .unwrap();
+        if let Some(single) = self.best_single {
+            if self.delete != [single] {
+                writeln!(
+                    out,
+                    "The smallest single directory that would do is {} ({}), {} more.",
+                    fs.path(single),
+                    sizes[single],
+                    sizes[single] - self.freed
+                )
+                .unwrap();
+            }
+        }
+        out
+    }
+}
+
+#[test]
+fn test_plan_example() {
+    let fs = FileSystem::from_transcript(crate::EXAMPLE).unwrap();
+    let p = plan(&fs, CleanupConfig::default()).unwrap();
+    let d = fs.child(FileSystem::ROOT, "d").unwrap();
+    assert_eq!(p.needed, 8381165);
+    assert_eq!(p.delete, vec![d]);
+    assert_eq!(p.freed, 24933642);
+    assert_eq!(p.best_single, Some(d));
+    assert_eq!(
+        p.explain(&fs),
+        "48381165 of 70000000 used, 21618835 free; the update needs 30000000.
+At least 8381165 more must be freed.
+Delete /d (24933642)
+This frees 24933642, 16552477 more than needed.
+"
+    );
+}
+
+#[test]
+fn test_plan_combines_directories() {
+    let input = "$ ls
+dir big
+dir x
+dir y
+$ cd big
+$ ls
+100 f
+$ cd /x
+$ ls
+45 f
+dir inner
+$ cd inner
+$ ls
+30 g
+$ cd /y
+$ ls
+30 f";
+    let fs = FileSystem::from_transcript(input).unwrap();
+    let x = fs.child(FileSystem::ROOT, "x").unwrap();
+    let inner = fs.child(x, "inner").unwrap();
+    let y = fs.child(FileSystem::ROOT, "y").unwrap();
+
+    // 205 used and 95 free, so 58 more are needed. /x is the best single
+    // directory at 75, but /x/inner and /y together are only 60.
+    let config = CleanupConfig {
+        disk_size: 300,
+        update_size: 153,
+    };
+    let p = plan(&fs, config).unwrap();
+    assert_eq!(p.needed, 58);
+    assert_eq!(p.delete, vec![inner, y]);
+    assert_eq!(p.freed, 60);
+    assert_eq!(p.best_single, Some(x));
+    assert!(p
+        .explain(&fs)
+        .ends_with("The smallest single directory that would do is /x (75), 15 more.\n"));
+
+    let config = CleanupConfig {
+        disk_size: 300,
+        update_size: 170,
+    };
+    let p = plan(&fs, config).unwrap();
+    assert_eq!(p.delete, vec![x]);
+    assert_eq!(p.freed, 75);
+}
+
+#[test]
+fn test_plan_edge_cases() {
+    let fs = FileSystem::from_transcript(crate::EXAMPLE).unwrap();
+
+    let config = CleanupConfig {
+        disk_size: 70_000_000,
+        update_size: 1_000,
+    };
+    let p = plan(&fs, config).unwrap();
+    assert_eq!(p.needed, 0);
+    assert!(p.delete.is_empty());
+    assert!(p.explain(&fs).ends_with("Nothing needs to be deleted.\n"));
+
+    // Already over capacity.
+    let config = CleanupConfig {
+        disk_size: 40_000_000,
+        update_size: 1_000,
+    };
+    assert_eq!(config.needed(48381165), 1_000);
+    assert!(plan(&fs, config).is_some());
+
+    let config = CleanupConfig {
+        disk_size: 70_000_000,
+        update_size: 70_000_001,
+    };
+    assert!(plan(&fs, config).is_none());
+}
+
+#[test]
+fn test_plan_skips_root_and_empty_directories() {
+    // /a is empty, so it can't help; /b alone frees enough.
+    let fs = FileSystem::from_transcript("$ ls\n100 f\ndir a\ndir b\n$ cd b\n$ ls\n60 g").unwrap();
+    let b = fs.lookup("/b").unwrap();
+    let config = CleanupConfig {
+        disk_size: 300,
+        update_size: 150,
+    };
+    let p = plan(&fs, config).unwrap();
+    assert_eq!(p.delete, vec![b]);
+    assert_eq!(p.best_single, Some(b));
+    assert!(p
+        .explain(&fs)
+        .ends_with("Delete /b (60)\nThis frees 60, 50 more than needed.\n"));
+
+    // The root is as big as /b, but is never deleted.
+    let fs = FileSystem::from_transcript("$ ls\ndir b\n$ cd b\n$ ls\n60 g").unwrap();
+    let b = fs.lookup("/b").unwrap();
+    let config = CleanupConfig {
+        disk_size: 100,
+        update_size: 90,
+    };
+    let p = plan(&fs, config).unwrap();
+    assert_eq!(p.delete, vec![b]);
+    assert_eq!(p.best_single, Some(b));
+
+    // Nothing but the root would free enough.
+    let fs = FileSystem::from_transcript("$ ls\n60 f\ndir b\n$ cd b\n$ ls\n10 g").unwrap();
+    let config = CleanupConfig {
+        disk_size: 100,
+        update_size: 90,
+    };
+    assert!(plan(&fs, config).is_none());
+}
+
+#[test]
+fn test_plan_prefers_fewest_and_deepest() {
+    // 185 used and 115 free, so 58 more are needed. /x, /y, and /z add up to
+    // 60, as do /u and /v. /p holds nothing but /p/q, so they're the same
+    // size.
+    let input = "$ ls
+dir x
+dir y
+dir z
+dir u
+dir v
+dir p
+$ cd x
+$ ls
+20 f
+$ cd /y
+$ ls
+20 f
+$ cd /z
+$ ls
+20 f
+$ cd /u
+$ ls
+25 f
+$ cd /v
+$ ls
+35 f
+$ cd /p
+$ ls
+dir q
+$ cd q
+$ ls
+65 f";
+    let fs = FileSystem::from_transcript(input).unwrap();
+    let lookup = |path| fs.lookup(path).unwrap();
+    let config = CleanupConfig {
+        disk_size: 300,
+        update_size: 173,
+    };
+    let p = plan(&fs, config).unwrap();
+    assert_eq!(p.needed, 58);
+    assert_eq!(p.delete, vec![lookup("/u"), lookup("/v")]);
+    assert_eq!(p.freed, 60);
+    assert_eq!(p.best_single, Some(lookup("/p/q")));
+
+    let config = CleanupConfig {
+        disk_size: 300,
+        update_size: 178,
+    };
+    let p = plan(&fs, config).unwrap();
+    assert_eq!(p.needed, 63);
+    assert_eq!(p.delete, vec![lookup("/p/q")]);
+    assert_eq!(p.freed, 65);
+}
@@ -1,4 +1,11 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Write},
+};
+
+pub mod cleanup;
+pub mod localfs;
+pub mod query;
 
 #[derive(Debug, Eq, PartialEq)]
 enum Line<'a> {
@@ -7,13 +14,13 @@ enum Line<'a> {
 }
 
 impl<'a> Line<'a> {
-    fn parse_borrowed(line: &'a str) -> Option<Self> {
+    /// `after_pwd` says whether the previous line was `$ pwd`, the only
+    /// place a bare path is valid output.
+    fn parse_borrowed(line: &'a str, after_pwd: bool) -> Option<Self> {
         if let Some(c) = Command::parse_borrowed(line) {
             Some(Line::Command(c))
-        } else if let Some(o) = Output::parse_borrowed(line) {
-            Some(Line::Output(o))
         } else {
-            None
+            Output::parse_borrowed(line, after_pwd).map(Line::Output)
         }
     }
 }
@@ -21,54 +28,56 @@ impl<'a> Line<'a> {
 #[test]
 fn test_parse_line() {
     assert_eq!(
-        Line::parse_borrowed("232179 vqqcvgts.vrc"),
+        Line::parse_borrowed("232179 vqqcvgts.vrc", false),
         Some(Line::Output(Output::FileStat(FileStat {
             name: "vqqcvgts.vrc",
             size: 232179
         })))
     );
     assert_eq!(
-        Line::parse_borrowed("dir bshmsns"),
+        Line::parse_borrowed("dir bshmsns", false),
         Some(Line::Output(Output::DirStat(DirStat { name: "bshmsns" })))
     );
     assert_eq!(
-        Line::parse_borrowed("$ cd /"),
+        Line::parse_borrowed("$ cd /", false),
         Some(Line::Command(Command::Cd("/")))
     );
     assert_eq!(
-        Line::parse_borrowed("$ ls"),
-        Some(Line::Command(Command::Ls))
+        Line::parse_borrowed("$ ls", false),
+        Some(Line::Command(Command::Ls(None)))
+    );
+    assert_eq!(
+        Line::parse_borrowed("/a/e", true),
+        Some(Line::Output(Output::WorkingDir("/a/e")))
     );
+    assert_eq!(Line::parse_borrowed("/a/e", false), None);
+    assert_eq!(Line::parse_borrowed("$ rm -rf /", false), None);
+    assert_eq!(Line::parse_borrowed("total 0", false), None);
 }
 
 #[derive(Debug, Eq, PartialEq)]
 enum Command<'a> {
+    /// `cd` without an argument is `cd ~`.
     Cd(&'a str),
-    Ls,
+    Ls(Option<&'a str>),
+    Pwd,
 }
 
 impl<'a> Command<'a> {
     fn parse_borrowed(line: &'a str) -> Option<Self> {
-        let mut parts = line.split(' ');
-        let first = parts.next()?;
-        if first != "$" {
-            return None;
-        }
-        let second = parts.next()?;
-        if second == "cd" {
-            let dirname = parts.next()?;
-            if parts.next().is_some() {
-                return None;
-            }
-            return Some(Command::Cd(dirname));
+        let command = line.strip_prefix("$ ")?;
+        let (name, arg) = match command.split_once(' ') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (command, None),
+        };
+        match (name, arg) {
+            ("cd", arg) => Some(Command::Cd(arg.unwrap_or("~"))),
+            // Options aren't supported; don't mistake them for a path.
+            ("ls", Some(arg)) if arg.starts_with('-') => None,
+            ("ls", arg) => Some(Command::Ls(arg)),
+            ("pwd", None) => Some(Command::Pwd),
+            _ => None,
         }
-        if second == "ls" {
-            if parts.next().is_some() {
-                return None;
-            }
-            return Some(Command::Ls);
-        }
-        None
     }
 }
 
@@ -77,23 +86,42 @@ fn test_parse_command() {
     assert_eq!(Command::parse_borrowed("232179 vqqcvgts.vrc"), None);
     assert_eq!(Command::parse_borrowed("dir bshmsns"), None);
     assert_eq!(Command::parse_borrowed("$ cd /"), Some(Command::Cd("/")));
-    assert_eq!(Command::parse_borrowed("$ ls"), Some(Command::Ls));
+    assert_eq!(Command::parse_borrowed("$ ls"), Some(Command::Ls(None)));
+    assert_eq!(
+        Command::parse_borrowed("$ cd /x/y"),
+        Some(Command::Cd("/x/y"))
+    );
+    assert_eq!(
+        Command::parse_borrowed("$ cd my dir"),
+        Some(Command::Cd("my dir"))
+    );
+    assert_eq!(Command::parse_borrowed("$ cd"), Some(Command::Cd("~")));
+    assert_eq!(
+        Command::parse_borrowed("$ ls a/b"),
+        Some(Command::Ls(Some("a/b")))
+    );
+    assert_eq!(Command::parse_borrowed("$ ls -l"), None);
+    assert_eq!(Command::parse_borrowed("$ pwd"), Some(Command::Pwd));
+    assert_eq!(Command::parse_borrowed("$ pwd -P"), None);
+    assert_eq!(Command::parse_borrowed("$ cat f"), None);
 }
 
 #[derive(Debug, Eq, PartialEq)]
 enum Output<'a> {
     DirStat(DirStat<'a>),
     FileStat(FileStat<'a>),
+    /// Output of `pwd`.
+    WorkingDir(&'a str),
 }
 
 impl<'a> Output<'a> {
-    fn parse_borrowed(line: &'a str) -> Option<Self> {
+    fn parse_borrowed(line: &'a str, after_pwd: bool) -> Option<Self> {
         if let Some(d) = DirStat::parse_borrowed(line) {
             Some(Output::DirStat(d))
-        } else if let Some(f) = FileStat::parse_borrowed(line) {
-            Some(Output::FileStat(f))
+        } else if after_pwd && line.starts_with('/') {
+            Some(Output::WorkingDir(line))
         } else {
-            None
+            FileStat::parse_borrowed(line).map(Output::FileStat)
         }
     }
 }
@@ -101,16 +129,21 @@ impl<'a> Output<'a> {
 #[test]
 fn test_parse_output() {
     assert_eq!(
-        Output::parse_borrowed("232179 vqqcvgts.vrc"),
+        Output::parse_borrowed("232179 vqqcvgts.vrc", false),
         Some(Output::FileStat(FileStat {
             name: "vqqcvgts.vrc",
             size: 232179
         }))
     );
     assert_eq!(
-        Output::parse_borrowed("dir bshmsns"),
+        Output::parse_borrowed("dir bshmsns", false),
         Some(Output::DirStat(DirStat { name: "bshmsns" }))
     );
+    assert_eq!(
+        Output::parse_borrowed("/", true),
+        Some(Output::WorkingDir("/"))
+    );
+    assert_eq!(Output::parse_borrowed("/", false), None);
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -120,13 +153,8 @@ struct DirStat<'a> {
 
 impl<'a> DirStat<'a> {
     fn parse_borrowed(line: &'a str) -> Option<Self> {
-        let mut parts = line.split(' ');
-        let dir_token = parts.next()?;
-        if dir_token != "dir" {
-            return None;
-        }
-        let name = parts.next()?;
-        if parts.next().is_some() {
+        let name = line.strip_prefix("dir ")?;
+        if name.is_empty() {
             return None;
         }
         Some(DirStat { name })
@@ -140,6 +168,13 @@ fn test_parse_dirstat() {
         DirStat::parse_borrowed("dir bshmsns"),
         Some(DirStat { name: "bshmsns" })
     );
+    assert_eq!(
+        DirStat::parse_borrowed("dir My Documents"),
+        Some(DirStat {
+            name: "My Documents"
+        })
+    );
+    assert_eq!(DirStat::parse_borrowed("dir "), None);
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -150,10 +185,8 @@ struct FileStat<'a> {
 
 impl<'a> FileStat<'a> {
     fn parse_borrowed(line: &'a str) -> Option<Self> {
-        let mut parts = line.split(' ');
-        let size_str = parts.next()?;
-        let name = parts.next()?;
-        if parts.next().is_some() {
+        let (size_str, name) = line.split_once(' ')?;
+        if name.is_empty() {
             return None;
         }
         let size: usize = size_str.parse().ok()?;
@@ -171,51 +204,639 @@ fn test_parse_filestat() {
         })
     );
     assert_eq!(FileStat::parse_borrowed("dir bshmsns"), None);
+    assert_eq!(
+        FileStat::parse_borrowed("12 notes for today.txt"),
+        Some(FileStat {
+            name: "notes for today.txt",
+            size: 12
+        })
+    );
+    assert_eq!(FileStat::parse_borrowed("12"), None);
 }
 
-fn get_dir_sizes(input: &str) -> HashMap<String, usize> {
-    let lines = input.lines().map(|l| Line::parse_borrowed(l).unwrap());
-    let mut cwd = Vec::<&str>::new();
-    let mut sizes = HashMap::<String, usize>::new();
-    for l in lines {
-        match l {
-            Line::Command(c) => {
-                match c {
-                    Command::Cd(dirname) => {
-                        if dirname == "/" {
-                            cwd.clear();
-                        } else if dirname == ".." {
-                            cwd.pop();
-                        } else {
-                            cwd.push(dirname);
-                        }
-                    }
-                    Command::Ls => {
-                        // ignore
+/// Why a transcript line couldn't be applied.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TranscriptErrorKind {
+    /// Neither a command nor command output we understand.
+    Unrecognized,
+    /// A path went through something that isn't a directory.
+    NotADirectory(String),
+    /// A name was listed as a file in one place and a directory in another.
+    KindConflict(String),
+    /// `pwd` printed something other than the directory we think we're in.
+    WrongWorkingDir { expected: String },
+}
+
+impl Display for TranscriptErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranscriptErrorKind::Unrecognized => write!(f, "unrecognized line"),
+            TranscriptErrorKind::NotADirectory(path) => write!(f, "{path} is not a directory"),
+            TranscriptErrorKind::KindConflict(path) => {
+                write!(f, "{path} is both a file and a directory")
+            }
+            TranscriptErrorKind::WrongWorkingDir { expected } => {
+                write!(f, "expected working directory {expected}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TranscriptError {
+    /// 1-based.
+    pub line: usize,
+    pub text: String,
+    pub kind: TranscriptErrorKind,
+}
+
+impl Display for TranscriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}: {:?}", self.line, self.kind, self.text)
+    }
+}
+
+impl std::error::Error for TranscriptError {}
+
+/// Index of a node in a `FileSystem`.
+pub type NodeId = usize;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Directory {
+    pub name: String,
+    /// `None` only for the root.
+    pub parent: Option<NodeId>,
+    pub children: BTreeMap<String, NodeId>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct File {
+    pub name: String,
+    pub parent: NodeId,
+    pub size: usize,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Node {
+    Directory(Directory),
+    File(File),
+}
+
+impl Node {
+    pub fn name(&self) -> &str {
+        match self {
+            Node::Directory(d) => &d.name,
+            Node::File(f) => &f.name,
+        }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        match self {
+            Node::Directory(d) => d.parent,
+            Node::File(f) => Some(f.parent),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self, Node::Directory(_))
+    }
+}
+
+/// The directory tree described by a transcript. Nodes live in an arena and
+/// refer to each other by `NodeId`; a node's parent always has a smaller id
+/// than the node itself.
+#[derive(Debug, Eq, PartialEq)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl FileSystem {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new() -> Self {
+        FileSystem {
+            nodes: vec![Node::Directory(Directory {
+                name: "/".to_string(),
+                parent: None,
+                children: BTreeMap::new(),
+            })],
+        }
+    }
+
+    /// Builds the tree from `cd`, `ls`, and `pwd` commands and their output.
+    /// Listing a directory more than once, or cd-ing into one that was never
+    /// listed, is fine.
+    pub fn from_transcript(input: &str) -> Result<Self, TranscriptError> {
+        let mut fs = FileSystem::new();
+        let mut cwd = Self::ROOT;
+        // Directory that `ls` output goes into.
+        let mut listing = Self::ROOT;
+        let mut after_pwd = false;
+        for (line_idx, l) in input.lines().enumerate() {
+            if l.trim().is_empty() {
+                continue;
+            }
+            let error = |kind| TranscriptError {
+                line: line_idx + 1,
+                text: l.to_string(),
+                kind,
+            };
+            let line = Line::parse_borrowed(l, after_pwd)
+                .ok_or_else(|| error(TranscriptErrorKind::Unrecognized))?;
+            after_pwd = line == Line::Command(Command::Pwd);
+            match line {
+                Line::Command(Command::Cd(path)) => {
+                    cwd = fs.resolve(cwd, path).map_err(error)?;
+                    listing = cwd;
+                }
+                Line::Command(Command::Ls(path)) => {
+                    listing = fs.resolve(cwd, path.unwrap_or(".")).map_err(error)?;
+                }
+                Line::Command(Command::Pwd) => (),
+                Line::Output(Output::WorkingDir(path)) => {
+                    let expected = fs.path(cwd);
+                    if path != expected {
+                        return Err(error(TranscriptErrorKind::WrongWorkingDir { expected }));
                     }
                 }
+                Line::Output(Output::DirStat(d)) => {
+                    fs.add_dir(listing, d.name).map_err(error)?;
+                }
+                Line::Output(Output::FileStat(f)) => {
+                    fs.add_file(listing, f.name, f.size).map_err(error)?;
+                }
             }
-            Line::Output(o) => {
-                match o {
-                    Output::DirStat(d) => {
-                        // ignore
+        }
+        Ok(fs)
+    }
+
+    /// Follows `path` from `from`, creating any directories along the way
+    /// that haven't been seen yet. Both `/` and `~` are the root.
+    pub fn resolve(&mut self, from: NodeId, path: &str) -> Result<NodeId, TranscriptErrorKind> {
+        let (mut id, rest) = if let Some(rest) = path.strip_prefix('/') {
+            (Self::ROOT, rest)
+        } else if path == "~" {
+            (Self::ROOT, "")
+        } else if let Some(rest) = path.strip_prefix("~/") {
+            (Self::ROOT, rest)
+        } else {
+            (from, path)
+        };
+        for component in rest.split('/') {
+            id = match component {
+                "" | "." => id,
+                ".." => self.node(id).parent().unwrap_or(Self::ROOT),
+                name => match self.child(id, name) {
+                    Some(child) if !self.node(child).is_dir() => {
+                        return Err(TranscriptErrorKind::NotADirectory(self.path(child)));
                     }
-                    Output::FileStat(f) => {
-                        for i in 0..=cwd.len() {
-                            let path = cwd[0..cwd.len() - i].join("/");
-                            *sizes.entry(path).or_default() += f.size;
-                        }
+                    _ => self.add_dir(id, name)?,
+                },
+            };
+        }
+        Ok(id)
+    }
+
+    /// Finds the node at an absolute path like `/a/e/i` without creating
+    /// anything.
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        path.strip_prefix('/')?
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .try_fold(Self::ROOT, |id, name| self.child(id, name))
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        // There's always a root.
+        false
+    }
+
+    pub fn ids(&self) -> std::ops::Range<NodeId> {
+        0..self.nodes.len()
+    }
+
+    /// Children of a directory in the order they were first seen, which for
+    /// a listed directory is the order `ls` printed them in.
+    pub fn children(&self, id: NodeId) -> impl DoubleEndedIterator<Item = NodeId> {
+        let mut children: Vec<NodeId> = match self.node(id) {
+            Node::Directory(d) => d.children.values().copied().collect(),
+            Node::File(_) => Vec::new(),
+        };
+        // Ids are handed out in creation order.
+        children.sort();
+        children.into_iter()
+    }
+
+    pub fn child(&self, id: NodeId, name: &str) -> Option<NodeId> {
+        match self.node(id) {
+            Node::Directory(d) => d.children.get(name).copied(),
+            Node::File(_) => None,
+        }
+    }
+
+    fn add_child(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        node: Node,
+    ) -> Result<NodeId, TranscriptErrorKind> {
+        if !self.node(parent).is_dir() {
+            return Err(TranscriptErrorKind::NotADirectory(self.path(parent)));
+        }
+        if let Some(existing) = self.child(parent, name) {
+            if self.node(existing).is_dir() != node.is_dir() {
+                return Err(TranscriptErrorKind::KindConflict(self.path(existing)));
+            }
+            return Ok(existing);
+        }
+        let id = self.nodes.len();
+        self.nodes.push(node);
+        if let Node::Directory(d) = &mut self.nodes[parent] {
+            d.children.insert(name.to_string(), id);
+        }
+        Ok(id)
+    }
+
+    /// Returns the existing directory if there is one.
+    pub fn add_dir(&mut self, parent: NodeId, name: &str) -> Result<NodeId, TranscriptErrorKind> {
+        self.add_child(
+            parent,
+            name,
+            Node::Directory(Directory {
+                name: name.to_string(),
+                parent: Some(parent),
+                children: BTreeMap::new(),
+            }),
+        )
+    }
+
+    /// Returns the existing file if there is one, updating its size.
+    pub fn add_file(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        size: usize,
+    ) -> Result<NodeId, TranscriptErrorKind> {
+        let id = self.add_child(
+            parent,
+            name,
+            Node::File(File {
+                name: name.to_string(),
+                parent,
+                size,
+            }),
+        )?;
+        if let Node::File(f) = &mut self.nodes[id] {
+            f.size = size;
+        }
+        Ok(id)
+    }
+
+    /// Total size of every node, indexed by `NodeId`. A directory's size is
+    /// the sum of everything under it.
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.nodes.len()];
+        // Children come after their parents, so walking backwards finishes
+        // each node before adding it to its parent.
+        for id in self.ids().rev() {
+            if let Node::File(f) = self.node(id) {
+                sizes[id] += f.size;
+            }
+            if let Some(parent) = self.node(id).parent() {
+                sizes[parent] += sizes[id];
+            }
+        }
+        sizes
+    }
+
+    /// Names from the root down to `id`, not including the root.
+    pub fn path_components(&self, id: NodeId) -> Vec<&str> {
+        let mut components = Vec::new();
+        let mut id = id;
+        while let Some(parent) = self.node(id).parent() {
+            components.push(self.node(id).name());
+            id = parent;
+        }
+        components.reverse();
+        components
+    }
+
+    /// Absolute path, e.g. `/a/e`.
+    pub fn path(&self, id: NodeId) -> String {
+        format!("/{}", self.path_components(id).join("/"))
+    }
+
+    /// Number of directories between the root and `id`; the root is at depth
+    /// 0.
+    pub fn depth(&self, id: NodeId) -> usize {
+        std::iter::successors(self.node(id).parent(), |p| self.node(*p).parent()).count()
+    }
+
+    /// Indented listing in the format used by the puzzle statement, leaving
+    /// out anything deeper than `max_depth`.
+    pub fn render_tree(&self, max_depth: Option<usize>) -> String {
+        let mut out = String::new();
+        let mut stack = vec![(Self::ROOT, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            let indent = "  ".repeat(depth);
+            match self.node(id) {
+                Node::Directory(d) => {
+                    writeln!(out, "{indent}- {} (dir)", d.name).unwrap();
+                    if max_depth.map_or(true, |max| depth < max) {
+                        stack.extend(self.children(id).rev().map(|c| (c, depth + 1)));
                     }
                 }
+                Node::File(f) => {
+                    writeln!(out, "{indent}- {} (file, size={})", f.name, f.size).unwrap();
+                }
             }
         }
+        out
+    }
+
+    /// `du -h` style table of directory sizes, largest first, leaving out
+    /// directories deeper than `max_depth`.
+    pub fn render_du(&self, max_depth: Option<usize>) -> String {
+        let sizes = self.sizes();
+        let mut dirs: Vec<(usize, String)> = self
+            .ids()
+            .filter(|id| self.node(*id).is_dir())
+            .filter(|id| max_depth.map_or(true, |max| self.depth(*id) <= max))
+            .map(|id| (sizes[id], self.path(id)))
+            .collect();
+        dirs.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        let mut out = String::new();
+        for (size, path) in dirs {
+            writeln!(out, "{:>5}\t{path}", human_size(size)).unwrap();
+        }
+        out
     }
-    sizes
 }
 
-pub mod p1 {
-    use std::collections::HashMap;
+/// Formats `bytes` like `du -h`: powers of 1024, rounded up, with one
+/// decimal place below 10.
+fn human_size(bytes: usize) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    loop {
+        let rounded = if value < 10.0 {
+            (value * 10.0).ceil() / 10.0
+        } else {
+            value.ceil()
+        };
+        if rounded >= 1024.0 && unit + 1 < UNITS.len() {
+            value /= 1024.0;
+            unit += 1;
+            continue;
+        }
+        return if rounded < 10.0 {
+            format!("{rounded:.1}{}", UNITS[unit])
+        } else {
+            format!("{rounded:.0}{}", UNITS[unit])
+        };
+    }
+}
+
+#[test]
+fn test_human_size() {
+    assert_eq!(human_size(0), "0");
+    assert_eq!(human_size(1023), "1023");
+    assert_eq!(human_size(1024), "1.0K");
+    assert_eq!(human_size(1025), "1.1K");
+    assert_eq!(human_size(10 * 1024), "10K");
+    assert_eq!(human_size(94853), "93K");
+    assert_eq!(human_size(1024 * 1024 - 1), "1.0M");
+    assert_eq!(human_size(48381165), "47M");
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+/// Sizes of every directory, keyed by path relative to the root (so the
+/// root is `""`, and `/a/e` is `"a/e"`).
+fn get_dir_sizes(input: &str) -> HashMap<String, usize> {
+    let fs = FileSystem::from_transcript(input).unwrap_or_else(|e| panic!("{e}"));
+    let sizes = fs.sizes();
+    fs.ids()
+        .filter(|id| fs.node(*id).is_dir())
+        .map(|id| (fs.path_components(id).join("/"), sizes[id]))
+        .collect()
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = r#"$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k"#;
+
+#[test]
+fn test_filesystem() {
+    let fs = FileSystem::from_transcript(EXAMPLE).unwrap();
+    assert_eq!(fs.len(), 14);
+    let a = fs.child(FileSystem::ROOT, "a").unwrap();
+    let e = fs.child(a, "e").unwrap();
+    let i = fs.child(e, "i").unwrap();
+    assert_eq!(fs.path(i), "/a/e/i");
+    assert_eq!(fs.path(FileSystem::ROOT), "/");
+    assert_eq!(fs.lookup("/a/e/i"), Some(i));
+    assert_eq!(fs.lookup("/a/e/"), Some(e));
+    assert_eq!(fs.lookup("/"), Some(FileSystem::ROOT));
+    assert_eq!(fs.lookup("/a/x"), None);
+    assert_eq!(fs.lookup("/a/e/i/j"), None);
+    assert_eq!(fs.lookup("a"), None);
+    assert_eq!(
+        fs.node(i),
+        &Node::File(File {
+            name: "i".to_string(),
+            parent: e,
+            size: 584
+        })
+    );
+
+    let sizes = fs.sizes();
+    assert_eq!(sizes[e], 584);
+    assert_eq!(sizes[a], 94853);
+    assert_eq!(sizes[fs.child(FileSystem::ROOT, "d").unwrap()], 24933642);
+    assert_eq!(sizes[FileSystem::ROOT], 48381165);
+}
+
+#[test]
+fn test_render_tree() {
+    let fs = FileSystem::from_transcript(EXAMPLE).unwrap();
+    assert_eq!(
+        fs.render_tree(None),
+        "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+"
+    );
+    assert_eq!(
+        fs.render_tree(Some(1)),
+        "- / (dir)
+  - a (dir)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+"
+    );
+    assert_eq!(fs.render_tree(Some(0)), "- / (dir)\n");
+}
+
+#[test]
+fn test_render_du() {
+    let fs = FileSystem::from_transcript(EXAMPLE).unwrap();
+    assert_eq!(
+        fs.render_du(None),
+        "  47M\t/
+  24M\t/d
+  93K\t/a
+  584\t/a/e
+"
+    );
+    assert_eq!(fs.render_du(Some(0)), "  47M\t/\n");
+}
+
+#[test]
+fn test_filesystem_repeated_ls() {
+    let input = format!("{EXAMPLE}\n$ cd /\n$ ls\ndir a\n14848514 b.txt\n8504156 c.dat\ndir d");
+    assert_eq!(
+        FileSystem::from_transcript(&input).unwrap(),
+        FileSystem::from_transcript(EXAMPLE).unwrap()
+    );
+    assert_eq!(get_dir_sizes(&input), get_dir_sizes(EXAMPLE));
+}
+
+#[test]
+fn test_filesystem_empty_dirs() {
+    let fs = FileSystem::from_transcript("$ cd /\n$ ls\ndir empty\n10 f\n$ cd x\n$ ls").unwrap();
+    let sizes = get_dir_sizes("$ cd /\n$ ls\ndir empty\n10 f\n$ cd x\n$ ls");
+    assert_eq!(fs.len(), 4);
+    assert_eq!(sizes.get("empty"), Some(&0));
+    assert_eq!(sizes.get("x"), Some(&0));
+    assert_eq!(sizes.get(""), Some(&10));
+}
+
+#[test]
+fn test_extended_grammar() {
+    let input = "$ cd a/b/c
+$ ls
+10 my file.txt
+dir sub dir
+$ cd /x/y
+$ pwd
+/x/y
+$ ls /a/b
+20 notes
+$ ls sub
+5 z
+$ cd ~
+$ pwd
+/
+$ cd a/./b/../b/c/sub dir
+$ ls
+1 deep
+$ cd
+$ ls ../x/y/../..
+dir x";
+    let fs = FileSystem::from_transcript(input).unwrap();
+    let sizes = fs.sizes();
+    let lookup = |path| fs.lookup(path).unwrap();
+    assert_eq!(fs.path(lookup("/a/b/c/sub dir")), "/a/b/c/sub dir");
+    assert_eq!(sizes[lookup("/a/b/c")], 11);
+    assert_eq!(sizes[lookup("/a/b")], 31);
+    assert_eq!(sizes[lookup("/x/y/sub")], 5);
+    assert_eq!(sizes[FileSystem::ROOT], 36);
+    assert_eq!(fs.len(), 12);
+}
+
+#[test]
+fn test_transcript_errors() {
+    assert_eq!(
+        FileSystem::from_transcript("$ cd /\n$ ls\nbogus line").unwrap_err(),
+        TranscriptError {
+            line: 3,
+            text: "bogus line".to_string(),
+            kind: TranscriptErrorKind::Unrecognized
+        }
+    );
+    assert_eq!(
+        FileSystem::from_transcript("$ ls\n10 f\n$ cd f")
+            .unwrap_err()
+            .kind,
+        TranscriptErrorKind::NotADirectory("/f".to_string())
+    );
+    assert_eq!(
+        FileSystem::from_transcript("$ ls\n10 f\ndir f")
+            .unwrap_err()
+            .kind,
+        TranscriptErrorKind::KindConflict("/f".to_string())
+    );
+    assert_eq!(
+        FileSystem::from_transcript("$ ls -l\n10 f").unwrap_err(),
+        TranscriptError {
+            line: 1,
+            text: "$ ls -l".to_string(),
+            kind: TranscriptErrorKind::Unrecognized
+        }
+    );
+    assert_eq!(
+        FileSystem::from_transcript("$ pwd\n/\n/").unwrap_err().line,
+        3
+    );
+    let err = FileSystem::from_transcript("$ cd a\n\n$ pwd\n/b").unwrap_err();
+    assert_eq!(err.line, 4);
+    assert_eq!(
+        err.to_string(),
+        "line 4: expected working directory /a: \"/b\""
+    );
+}
+
+pub mod p1 {
     use super::*;
 
     pub fn solve(input: &str) -> usize {
@@ -253,23 +874,28 @@ $ ls
 }
 
 pub mod p2 {
-    use std::collections::HashMap;
-
     use super::*;
+    use crate::cleanup::CleanupConfig;
 
     pub fn solve(input: &str) -> usize {
-        const PARTITION_SZ: usize = 70000000;
-        const UPDATE_SZ: usize = 30000000;
+        solve_with(input, CleanupConfig::default())
+    }
+
+    /// Size of the smallest single directory that frees enough space for
+    /// the update, or 0 if there's already enough.
+    pub fn solve_with(input: &str, config: CleanupConfig) -> usize {
         let sizes = get_dir_sizes(input);
         let root_sz = *sizes.get("").unwrap();
-        let free = PARTITION_SZ - root_sz;
-        let needed = UPDATE_SZ - free;
+        let needed = config.needed(root_sz);
+        if needed == 0 {
+            return 0;
+        }
         sizes
             .values()
             .copied()
             .filter(|sz| *sz >= needed)
             .min()
-            .unwrap()
+            .expect("Not enough space even after deleting everything")
     }
 
     #[test]
@@ -298,5 +924,15 @@ $ ls
 5626152 d.ext
 7214296 k"#;
         assert_eq!(solve(input), 24933642);
+        assert_eq!(
+            solve_with(
+                input,
+                CleanupConfig {
+                    disk_size: 100_000_000,
+                    update_size: 30_000_000
+                }
+            ),
+            0
+        );
     }
 }
@@ -0,0 +1,242 @@
+//! Moving between transcripts and real directories on the local disk.
+
+use std::{
+    fmt::Write,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{FileSystem, Node, NodeId};
+
+/// Walks the directory at `root` and returns a transcript of `cd`-ing into
+/// and listing every directory under it, depth first with entries sorted by
+/// name. Anything that isn't a regular file or directory (e.g. a symlink) is
+/// left out.
+pub fn transcribe(root: &Path) -> io::Result<String> {
+    let mut transcript = String::from("$ cd /\n");
+    transcribe_dir(root, &mut transcript)?;
+    Ok(transcript)
+}
+
+fn transcribe_dir(dir: &Path, transcript: &mut String) -> io::Result<()> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().map_err(|name| {
+            io::Error::new(ErrorKind::InvalidData, format!("{name:?} isn't UTF-8"))
+        })?;
+        if name.contains('\n') {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{name:?} can't be written on one line"),
+            ));
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            dirs.push(name);
+        } else if file_type.is_file() {
+            let size = entry.metadata()?.len();
+            files.push((name, size));
+        }
+    }
+    dirs.sort();
+    files.sort();
+
+    transcript.push_str("$ ls\n");
+    for name in &dirs {
+        writeln!(transcript, "dir {name}").unwrap();
+    }
+    for (name, size) in &files {
+        writeln!(transcript, "{size} {name}").unwrap();
+    }
+    for name in &dirs {
+        // A bare `~` would mean the root.
+        let cd_arg = if name == "~" { "./~" } else { name };
+        writeln!(transcript, "$ cd {cd_arg}").unwrap();
+        transcribe_dir(&dir.join(name), transcript)?;
+        transcript.push_str("$ cd ..\n");
+    }
+    Ok(())
+}
+
+/// Where `id` lives if `fs` is materialized at `dest`.
+fn disk_path(fs: &FileSystem, dest: &Path, id: NodeId) -> PathBuf {
+    let mut path = dest.to_path_buf();
+    path.extend(fs.path_components(id));
+    path
+}
+
+/// Recreates `fs` under `dest`, which must already exist. Files are created
+/// sparse, so they take up next to no disk space whatever their size.
+pub fn materialize(fs: &FileSystem, dest: &Path) -> io::Result<()> {
+    // Parents have smaller ids than their children.
+    for id in fs.ids().skip(1) {
+        let path = disk_path(fs, dest, id);
+        match fs.node(id) {
+            Node::Directory(_) => fs::create_dir(path)?,
+            Node::File(f) => fs::File::create(path)?.set_len(f.size as u64)?,
+        }
+    }
+    Ok(())
+}
+
+/// A directory whose size according to `du` doesn't match the model.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SizeMismatch {
+    pub path: String,
+    pub expected: usize,
+    /// Negative if `du` counted less than the directory entries alone.
+    pub du: isize,
+}
+
+/// Runs GNU `du --apparent-size` over `fs` materialized at `dest` and
+/// compares each directory's total with `FileSystem::sizes`.
+///
+/// `du` also counts the directory entries themselves, whose size depends on
+/// the OS's filesystem, so those are measured and subtracted first.
+pub fn du_mismatches(fs: &FileSystem, dest: &Path) -> io::Result<Vec<SizeMismatch>> {
+    let output = Command::new("du")
+        .arg("--apparent-size")
+        .arg("--block-size=1")
+        .arg(dest)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+    let stdout =
+        String::from_utf8(output.stdout).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+    // Size of each directory entry plus all of those below it.
+    let mut dir_overhead = vec![0; fs.len()];
+    for id in fs.ids().rev() {
+        if fs.node(id).is_dir() {
+            dir_overhead[id] += fs::metadata(disk_path(fs, dest, id))?.len() as usize;
+        }
+        if let Some(parent) = fs.node(id).parent() {
+            dir_overhead[parent] += dir_overhead[id];
+        }
+    }
+
+    let sizes = fs.sizes();
+    let mut mismatches = Vec::new();
+    for line in stdout.lines() {
+        let (du_size, path) = line
+            .split_once('\t')
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, line.to_string()))?;
+        let du_size: usize = du_size
+            .parse()
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        let relative = Path::new(path)
+            .strip_prefix(dest)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        let mut id = FileSystem::ROOT;
+        for component in relative.iter() {
+            let name = component.to_str().unwrap();
+            id = fs.child(id, name).ok_or_else(|| {
+                io::Error::new(ErrorKind::NotFound, format!("{path} isn't in the model"))
+            })?;
+        }
+        let du = du_size as isize - dir_overhead[id] as isize;
+        if du != sizes[id] as isize {
+            mismatches.push(SizeMismatch {
+                path: fs.path(id),
+                expected: sizes[id],
+                du,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// A fresh, empty directory under the system temp directory.
+pub fn make_temp_dir(prefix: &str) -> io::Result<PathBuf> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("{prefix}-{}-{nanos}", std::process::id()));
+    fs::create_dir(&dir)?;
+    Ok(dir)
+}
+
+/// A temp directory that's removed when dropped, even if a test fails.
+#[cfg(test)]
+struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    fn new() -> Self {
+        TempDir(make_temp_dir("day7-test").unwrap())
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_materialize_and_transcribe() {
+    let fs = FileSystem::from_transcript(crate::EXAMPLE).unwrap();
+    let temp = TempDir::new();
+    let dir = &temp.0;
+    materialize(&fs, dir).unwrap();
+    assert_eq!(
+        fs::metadata(dir.join("d").join("d.log")).unwrap().len(),
+        8033020
+    );
+
+    let transcript = transcribe(dir).unwrap();
+    assert!(transcript.starts_with("$ cd /\n$ ls\ndir a\ndir d\n14848514 b.txt\n"));
+    assert_eq!(
+        crate::get_dir_sizes(&transcript),
+        crate::get_dir_sizes(crate::EXAMPLE)
+    );
+}
+
+/// Needs GNU `du`.
+#[cfg(target_os = "linux")]
+#[test]
+fn test_du_mismatches() {
+    let fs = FileSystem::from_transcript(crate::EXAMPLE).unwrap();
+    let temp = TempDir::new();
+    materialize(&fs, &temp.0).unwrap();
+    assert_eq!(du_mismatches(&fs, &temp.0).unwrap(), vec![]);
+
+    fs::write(temp.0.join("a").join("e").join("i"), "").unwrap();
+    assert_eq!(
+        du_mismatches(&fs, &temp.0).unwrap(),
+        ["/a/e", "/a", "/"].map(|path| {
+            let expected = fs.sizes()[fs.lookup(path).unwrap()];
+            SizeMismatch {
+                path: path.to_string(),
+                expected,
+                du: expected as isize - 584,
+            }
+        })
+    );
+}
+
+#[test]
+fn test_transcribe_awkward_names() {
+    let temp = TempDir::new();
+    let dir = &temp.0;
+    fs::create_dir_all(dir.join("~").join("with space")).unwrap();
+    fs::write(dir.join("~").join("with space").join("a b"), "hello").unwrap();
+
+    let transcript = transcribe(dir).unwrap();
+    let fs = FileSystem::from_transcript(&transcript).unwrap();
+    assert_eq!(fs.sizes()[FileSystem::ROOT], 5);
+    assert_eq!(
+        fs.render_du(None),
+        "    5\t/\n    5\t/~\n    5\t/~/with space\n"
+    );
+}
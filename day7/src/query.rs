@@ -0,0 +1,271 @@
+//! `find`-style searches over a `FileSystem`.
+
+use std::str::FromStr;
+
+use crate::{FileSystem, Node, NodeId};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Kind {
+    File,
+    Dir,
+}
+
+impl FromStr for Kind {
+    type Err = ();
+
+    /// `f` or `d`, as in `find -type`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f" => Ok(Kind::File),
+            "d" => Ok(Kind::Dir),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A numeric test written like `find -size`: `+N` is more than N, `-N` is
+/// less than N, and a bare `N` is exactly N.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Cmp {
+    Less(usize),
+    Exactly(usize),
+    More(usize),
+}
+
+impl Cmp {
+    pub fn matches(&self, value: usize) -> bool {
+        match *self {
+            Cmp::Less(n) => value < n,
+            Cmp::Exactly(n) => value == n,
+            Cmp::More(n) => value > n,
+        }
+    }
+}
+
+impl FromStr for Cmp {
+    type Err = ();
+
+    /// The number may end in `K`, `M`, or `G` for powers of 1024.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (make, s): (fn(usize) -> Cmp, _) = if let Some(s) = s.strip_prefix('+') {
+            (Cmp::More, s)
+        } else if let Some(s) = s.strip_prefix('-') {
+            (Cmp::Less, s)
+        } else {
+            (Cmp::Exactly, s)
+        };
+        let (digits, scale) = match s.char_indices().last() {
+            Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+            Some((i, 'M')) => (&s[..i], 1 << 20),
+            Some((i, 'G')) => (&s[..i], 1 << 30),
+            _ => (s, 1),
+        };
+        let n: usize = digits.parse().map_err(|_| ())?;
+        n.checked_mul(scale).map(make).ok_or(())
+    }
+}
+
+#[test]
+fn test_parse_cmp() {
+    assert_eq!("+1M".parse(), Ok(Cmp::More(1 << 20)));
+    assert_eq!("-100".parse(), Ok(Cmp::Less(100)));
+    assert_eq!("3".parse(), Ok(Cmp::Exactly(3)));
+    assert_eq!("2k".parse(), Ok(Cmp::Exactly(2048)));
+    assert_eq!("+".parse::<Cmp>(), Err(()));
+    assert_eq!("1X".parse::<Cmp>(), Err(()));
+    assert!(Cmp::More(5).matches(6));
+    assert!(!Cmp::More(5).matches(5));
+    assert!(Cmp::Less(5).matches(4));
+}
+
+/// A shell-style pattern for names: `*` matches any run of characters and
+/// `?` matches any one character.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Glob(Vec<char>);
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        Glob(pattern.chars().collect())
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let name: Vec<char> = name.chars().collect();
+        let (mut p, mut n) = (0, 0);
+        // Where to resume if the characters after the last `*` stop
+        // matching: the position after that `*`, and how much of `name` it
+        // has swallowed so far.
+        let mut backtrack = None;
+        while n < name.len() {
+            match self.0.get(p) {
+                Some('*') => {
+                    backtrack = Some((p + 1, n));
+                    p += 1;
+                }
+                Some(c) if *c == '?' || *c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match backtrack {
+                    Some((star_p, star_n)) => {
+                        backtrack = Some((star_p, star_n + 1));
+                        p = star_p;
+                        n = star_n + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.0[p..].iter().all(|c| *c == '*')
+    }
+}
+
+#[test]
+fn test_glob() {
+    assert!(Glob::new("*.txt").matches("b.txt"));
+    assert!(!Glob::new("*.txt").matches("b.txt.bak"));
+    assert!(Glob::new("d.*").matches("d.log"));
+    assert!(Glob::new("?").matches("i"));
+    assert!(!Glob::new("?").matches("ij"));
+    assert!(Glob::new("*").matches(""));
+    assert!(Glob::new("a*b*c").matches("aXbYbZc"));
+    assert!(!Glob::new("a*b*c").matches("aXbYbZ"));
+    assert!(Glob::new("").matches(""));
+    assert!(!Glob::new("").matches("a"));
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Predicate {
+    Type(Kind),
+    /// A file's size, or everything under a directory.
+    Size(Cmp),
+    /// Only the files directly in a directory, not in its subdirectories.
+    /// Files never match.
+    DirectSize(Cmp),
+    /// Levels below where the search started.
+    Depth(Cmp),
+    Name(Glob),
+}
+
+/// Everything under `start`, including `start` itself, that matches all of
+/// `predicates`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Query {
+    pub start: NodeId,
+    pub predicates: Vec<Predicate>,
+}
+
+impl Query {
+    pub fn new(start: NodeId) -> Self {
+        Query {
+            start,
+            predicates: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, predicate: Predicate) -> Self {
+        self.predicates.push(predicate);
+        self
+    }
+
+    /// Matching nodes in the order `find` would print them: each directory
+    /// before its contents, in listing order.
+    pub fn run(&self, fs: &FileSystem) -> Vec<NodeId> {
+        let sizes = fs.sizes();
+        let direct = direct_sizes(fs);
+        let mut found = Vec::new();
+        let mut stack = vec![(self.start, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            let matches = self.predicates.iter().all(|p| match p {
+                Predicate::Type(Kind::File) => !fs.node(id).is_dir(),
+                Predicate::Type(Kind::Dir) => fs.node(id).is_dir(),
+                Predicate::Size(cmp) => cmp.matches(sizes[id]),
+                Predicate::DirectSize(cmp) => fs.node(id).is_dir() && cmp.matches(direct[id]),
+                Predicate::Depth(cmp) => cmp.matches(depth),
+                Predicate::Name(glob) => glob.matches(fs.node(id).name()),
+            });
+            if matches {
+                found.push(id);
+            }
+            stack.extend(fs.children(id).rev().map(|c| (c, depth + 1)));
+        }
+        found
+    }
+}
+
+/// Total size of the files directly in each directory, indexed by `NodeId`.
+/// Zero for files.
+pub fn direct_sizes(fs: &FileSystem) -> Vec<usize> {
+    let mut direct = vec![0; fs.len()];
+    for id in fs.ids() {
+        if let Node::File(f) = fs.node(id) {
+            direct[f.parent] += f.size;
+        }
+    }
+    direct
+}
+
+/// The biggest file directly in `dir`, the first listed if there's a tie.
+pub fn largest_file(fs: &FileSystem, dir: NodeId) -> Option<NodeId> {
+    fs.children(dir)
+        .filter_map(|id| match fs.node(id) {
+            Node::File(f) => Some((id, f.size)),
+            Node::Directory(_) => None,
+        })
+        .rev()
+        .max_by_key(|(_, size)| *size)
+        .map(|(id, _)| id)
+}
+
+#[cfg(test)]
+fn paths(fs: &FileSystem, ids: &[NodeId]) -> Vec<String> {
+    ids.iter().map(|id| fs.path(*id)).collect()
+}
+
+#[test]
+fn test_query() {
+    let fs = FileSystem::from_transcript(crate::EXAMPLE).unwrap();
+    let a = fs.lookup("/a").unwrap();
+
+    let all = Query::new(FileSystem::ROOT).run(&fs);
+    assert_eq!(all.len(), fs.len());
+    assert_eq!(fs.path(all[0]), "/");
+    assert_eq!(fs.path(all[1]), "/a");
+    assert_eq!(fs.path(all[2]), "/a/e");
+
+    let big_files = Query::new(FileSystem::ROOT)
+        .with(Predicate::Type(Kind::File))
+        .with(Predicate::Size("+7M".parse().unwrap()));
+    assert_eq!(
+        paths(&fs, &big_files.run(&fs)),
+        ["/b.txt", "/c.dat", "/d/d.log"]
+    );
+
+    let under_a = Query::new(a).with(Predicate::Size(Cmp::More(3000)));
+    assert_eq!(paths(&fs, &under_a.run(&fs)), ["/a", "/a/f", "/a/h.lst"]);
+
+    let shallow = Query::new(FileSystem::ROOT)
+        .with(Predicate::Depth(Cmp::Less(2)))
+        .with(Predicate::Name(Glob::new("*.*")));
+    assert_eq!(paths(&fs, &shallow.run(&fs)), ["/b.txt", "/c.dat"]);
+
+    let named = Query::new(FileSystem::ROOT).with(Predicate::Name(Glob::new("d*")));
+    assert_eq!(paths(&fs, &named.run(&fs)), ["/d", "/d/d.log", "/d/d.ext"]);
+
+    // /a's own files are under 100K, and /a/e only has `i`.
+    let light_dirs = Query::new(FileSystem::ROOT).with(Predicate::DirectSize(Cmp::Less(100_000)));
+    assert_eq!(paths(&fs, &light_dirs.run(&fs)), ["/a", "/a/e"]);
+}
+
+#[test]
+fn test_largest_file() {
+    let fs = FileSystem::from_transcript(crate::EXAMPLE).unwrap();
+    let largest = |dir| largest_file(&fs, fs.lookup(dir).unwrap()).map(|id| fs.path(id));
+    assert_eq!(largest("/").as_deref(), Some("/b.txt"));
+    assert_eq!(largest("/a").as_deref(), Some("/a/h.lst"));
+    assert_eq!(largest("/d").as_deref(), Some("/d/d.log"));
+
+    let fs = FileSystem::from_transcript("$ ls\n5 x\n5 y\ndir z").unwrap();
+    let x = fs.lookup("/x").unwrap();
+    assert_eq!(largest_file(&fs, FileSystem::ROOT), Some(x));
+    assert_eq!(largest_file(&fs, fs.lookup("/z").unwrap()), None);
+}
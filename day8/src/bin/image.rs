@@ -0,0 +1,28 @@
+use std::io::{Read, Write};
+
+use day8::{image, TreeHeights};
+
+/// Reads a height map from stdin and writes a binary PPM to stdout:
+/// `heights`, `visible` (trees seen from outside in green), or `scenic` (a
+/// heatmap with the best tree in blue). An optional second argument is the
+/// number of pixels per tree along each side.
+pub fn main() {
+    let mut args = std::env::args().skip(1);
+    let kind = args.next().expect("heights, visible, or scenic");
+    let factor: usize = args.next().map_or(1, |f| f.parse().unwrap());
+    if factor < 1 {
+        panic!("Pixels per tree must be at least 1, got {factor}");
+    }
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let heights: TreeHeights = input.parse().unwrap();
+    let picture = match kind.as_str() {
+        "heights" => image::height_map(&heights),
+        "visible" => image::visibility_image(&heights, &heights.visibilities()),
+        "scenic" => image::scenic_heatmap(&heights.scenic_scores()),
+        _ => panic!("Unknown image {kind}"),
+    };
+    let mut out = std::io::stdout().lock();
+    picture.scaled(factor).write_ppm(&mut out).unwrap();
+    out.flush().unwrap();
+}
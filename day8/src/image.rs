@@ -0,0 +1,161 @@
+//! Pictures of a forest as binary PPM/PGM images, one pixel (or square of
+//! pixels) per tree.
+
+use std::io::{self, Write};
+
+use crate::{TreeHeights, TreeValues, TreeVisibilities};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    /// RGB, row by row.
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    fn from_fn(
+        width: usize,
+        height: usize,
+        mut pixel: impl FnMut(usize, usize) -> [u8; 3],
+    ) -> Self {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(pixel(x, y));
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Blows every pixel up into a `factor` by `factor` square, so small
+    /// forests are big enough to look at.
+    pub fn scaled(&self, factor: usize) -> Image {
+        Image::from_fn(self.width * factor, self.height * factor, |x, y| {
+            self.pixel(x / factor, y / factor)
+        })
+    }
+
+    /// Writes a binary (P6) PPM.
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for pixel in &self.pixels {
+            out.write_all(pixel)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a binary (P5) PGM, converting each pixel to its luma.
+    pub fn write_pgm(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+        for [r, g, b] in &self.pixels {
+            let luma = (299 * *r as u32 + 587 * *g as u32 + 114 * *b as u32) / 1000;
+            out.write_all(&[luma as u8])?;
+        }
+        Ok(())
+    }
+}
+
+/// Where `value` falls between `min` and `max`, from 0 to 255.
+fn scale(value: f64, min: f64, max: f64) -> u8 {
+    if max <= min {
+        return 255;
+    }
+    ((value - min) / (max - min) * 255.0).round() as u8
+}
+
+/// Taller trees are lighter.
+pub fn height_map(heights: &TreeHeights) -> Image {
+    let values = heights.0.values();
+    let min = values.iter().copied().min().unwrap_or(0) as f64;
+    let max = values.iter().copied().max().unwrap_or(0) as f64;
+    Image::from_fn(heights.width(), heights.height(), |x, y| {
+        let gray = scale(heights.height_at(x, y) as f64, min, max);
+        [gray, gray, gray]
+    })
+}
+
+/// The height map with the trees that can be seen from outside the forest
+/// tinted green.
+pub fn visibility_image(heights: &TreeHeights, visibilities: &TreeVisibilities) -> Image {
+    let gray = height_map(heights);
+    Image::from_fn(heights.width(), heights.height(), |x, y| {
+        let [g, ..] = gray.pixel(x, y);
+        if *visibilities.0.get(x, y) {
+            [g / 4, 128 + g / 2, g / 4]
+        } else {
+            [g, g, g]
+        }
+    })
+}
+
+/// Scenic scores from black through red and yellow to white, with the best
+/// tree (the first, if there's a tie) in blue. Scores are shown on a log
+/// scale since a few trees tend to score far higher than the rest.
+pub fn scenic_heatmap(scores: &TreeValues<usize>) -> Image {
+    let values = scores.values();
+    let best = values
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, score)| **score)
+        .map(|(i, _)| i);
+    let max = values.iter().copied().max().unwrap_or(0) as f64;
+    Image::from_fn(scores.width(), scores.height(), |x, y| {
+        if Some(y * scores.width() + x) == best {
+            return [0, 64, 255];
+        }
+        let heat = scale((*scores.get(x, y) as f64).ln_1p(), 0.0, max.ln_1p()) as u32;
+        // Three ramps, one channel at a time.
+        let ramp = |start: u32| (heat * 3).saturating_sub(start).min(255) as u8;
+        [ramp(0), ramp(255), ramp(510)]
+    })
+}
+
+#[test]
+fn test_write() {
+    let image = Image::from_fn(2, 1, |x, _| [x as u8 * 255, 10, 20]);
+    let mut ppm = Vec::new();
+    image.write_ppm(&mut ppm).unwrap();
+    assert_eq!(ppm, b"P6\n2 1\n255\n\x00\x0a\x14\xff\x0a\x14");
+
+    let mut pgm = Vec::new();
+    image.scaled(2).write_pgm(&mut pgm).unwrap();
+    assert_eq!(pgm, b"P5\n4 2\n255\n\x08\x08\x54\x54\x08\x08\x54\x54");
+}
+
+#[test]
+fn test_images() {
+    let heights: TreeHeights = "30373\n25512\n65332\n33549\n35390".parse().unwrap();
+
+    let gray = height_map(&heights);
+    assert_eq!(gray.pixel(0, 0), [85; 3]);
+    assert_eq!(gray.pixel(4, 3), [255; 3]);
+    assert_eq!(gray.pixel(4, 4), [0; 3]);
+
+    // The 3 in the centre is hidden; the 5 above it isn't.
+    let visible = visibility_image(&heights, &heights.visibilities());
+    assert_eq!(visible.pixel(2, 2), [85; 3]);
+    assert_eq!(visible.pixel(2, 1), [35, 199, 35]);
+
+    let heat = scenic_heatmap(&heights.scenic_scores());
+    assert_eq!(heat.pixel(2, 3), [0, 64, 255]);
+    assert_eq!(heat.pixel(0, 0), [0, 0, 0]);
+    assert_eq!(heat.pixel(2, 1), [255, 255, 51]);
+}
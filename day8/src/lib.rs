@@ -1,7 +1,13 @@
-use std::{fmt::Debug, str::FromStr};
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
 
+pub mod image;
+
+/// One value per tree, stored row by row.
 #[derive(Eq, PartialEq, Debug)]
-struct TreeValues<T>
+pub struct TreeValues<T>
 where
     T: Eq + PartialEq + Debug,
 {
@@ -23,10 +29,23 @@ where
     pub fn get(&self, x: usize, y: usize) -> &T {
         &self.values[self.idx(x, y)]
     }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// All values, row by row.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
 }
 
 #[derive(Eq, PartialEq, Debug)]
-struct TreeHeights(TreeValues<u32>);
+pub struct TreeHeights(TreeValues<u32>);
 impl TreeHeights {
     pub fn height_at(&self, x: usize, y: usize) -> u32 {
         *self.0.get(x, y)
@@ -94,6 +113,82 @@ impl TreeHeights {
         })
     }
 
+    /// Scenic score of every tree. Each line of trees is scanned once per
+    /// direction, so this is linear in the number of trees; see
+    /// `scenic_score_at` for the definition.
+    pub fn scenic_scores(&self) -> TreeValues<usize> {
+        let (width, height) = (self.width(), self.height());
+        let mut scores = vec![1; width * height];
+        let mut apply = |line: &mut dyn Iterator<Item = (usize, usize)>| {
+            let line: Vec<(usize, usize)> = line.collect();
+            let heights = line.iter().map(|(x, y)| self.height_at(*x, *y));
+            for ((x, y), distance) in line.iter().zip(viewing_distances(heights)) {
+                scores[y * width + x] *= distance;
+            }
+        };
+        for y in 0..height {
+            apply(&mut (0..width).map(|x| (x, y)));
+            apply(&mut (0..width).rev().map(|x| (x, y)));
+        }
+        for x in 0..width {
+            apply(&mut (0..height).map(|y| (x, y)));
+            apply(&mut (0..height).rev().map(|y| (x, y)));
+        }
+        TreeValues {
+            width,
+            height,
+            values: scores,
+        }
+    }
+
+    /// Which trees someone standing on the tree at (`x`, `y`) with their eyes
+    /// at `eye_height` can see, looking in `directions`. Looking along a
+    /// line, a tree can be seen if its top is at a steeper angle than the
+    /// top of every tree in front of it, so looking down from high up shows
+    /// more than looking up from the ground. The tree they're standing on
+    /// doesn't count, and must be on the map.
+    pub fn visible_from(
+        &self,
+        x: usize,
+        y: usize,
+        eye_height: u32,
+        directions: Directions,
+    ) -> TreeVisibilities {
+        assert!(x < self.width());
+        assert!(y < self.height());
+        let mut visible = vec![false; self.width() * self.height()];
+        let eye = eye_height as i64;
+        for (dx, dy) in directions.steps() {
+            // Steepest (rise, run) so far; distances are in steps, which is
+            // fine since every step along a line is the same length.
+            let mut steepest: Option<(i64, i64)> = None;
+            let (mut tx, mut ty) = (x as isize, y as isize);
+            for run in 1.. {
+                (tx, ty) = (tx + dx, ty + dy);
+                if !(0..self.width() as isize).contains(&tx)
+                    || !(0..self.height() as isize).contains(&ty)
+                {
+                    break;
+                }
+                let (tx, ty) = (tx as usize, ty as usize);
+                let rise = self.height_at(tx, ty) as i64 - eye;
+                let hidden = matches!(steepest, Some((r, n)) if rise * n <= r * run);
+                if !hidden {
+                    visible[self.0.idx(tx, ty)] = true;
+                    steepest = Some((rise, run));
+                }
+            }
+        }
+        TreeVisibilities(TreeValues {
+            width: self.width(),
+            height: self.height(),
+            values: visible,
+        })
+    }
+
+    /// Scenic score of a single tree, found by walking outwards until the
+    /// view is blocked in each direction. `scenic_scores` is much faster for
+    /// the whole grid.
     pub fn scenic_score_at(&self, x: usize, y: usize) -> usize {
         let max_height = self.height_at(x, y);
 
@@ -133,36 +228,174 @@ impl TreeHeights {
     }
 }
 
-impl FromStr for TreeHeights {
-    type Err = ();
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Directions {
+    /// Along the rows and columns.
+    Cardinal,
+    /// Along the diagonals as well.
+    All,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut width = 0;
+impl Directions {
+    fn steps(&self) -> &'static [(isize, isize)] {
+        const ALL: [(isize, isize); 8] = [
+            (0, -1),
+            (1, 0),
+            (0, 1),
+            (-1, 0),
+            (1, -1),
+            (1, 1),
+            (-1, 1),
+            (-1, -1),
+        ];
+        match self {
+            Directions::Cardinal => &ALL[..4],
+            Directions::All => &ALL,
+        }
+    }
+}
+
+/// For each tree in a line, how many trees can be seen looking back towards
+/// the start of the line: up to and including the first one at least as
+/// tall, or all the way to the edge.
+fn viewing_distances(heights: impl Iterator<Item = u32>) -> Vec<usize> {
+    // Positions of the trees that could still block a view, tallest first.
+    let mut blockers: Vec<(usize, u32)> = Vec::new();
+    let mut distances = Vec::new();
+    for (i, height) in heights.enumerate() {
+        while matches!(blockers.last(), Some((_, h)) if *h < height) {
+            blockers.pop();
+        }
+        distances.push(blockers.last().map_or(i, |(j, _)| i - j));
+        blockers.push((i, height));
+    }
+    distances
+}
+
+#[test]
+fn test_viewing_distances() {
+    let distances = |heights: &[u32]| viewing_distances(heights.iter().copied());
+    assert_eq!(distances(&[]), Vec::<usize>::new());
+    assert_eq!(distances(&[3, 0, 3, 7, 3]), vec![0, 1, 2, 3, 1]);
+    assert_eq!(distances(&[5, 4, 3, 4, 9]), vec![0, 1, 1, 2, 4]);
+    assert_eq!(distances(&[2, 2, 2]), vec![0, 1, 1]);
+}
+
+impl TreeHeights {
+    /// Parses heights written as numbers separated by commas or whitespace,
+    /// one row per line, e.g. real elevation data exported as CSV.
+    pub fn parse_numbers(s: &str) -> Result<Self, HeightMapError> {
+        Self::from_rows(s, |row| {
+            let fields: Vec<&str> = if row.contains(',') {
+                row.split(',').map(str::trim).collect()
+            } else {
+                row.split_whitespace().collect()
+            };
+            fields
+                .into_iter()
+                .enumerate()
+                .map(|(col_idx, field)| field.parse().map_err(|_| (col_idx, field.to_string())))
+                .collect()
+        })
+    }
+
+    /// Builds the grid from the non-blank lines of `s`, using `parse_row` to
+    /// get each row's heights or the index and text of the first bad one.
+    fn from_rows(
+        s: &str,
+        parse_row: impl Fn(&str) -> Result<Vec<u32>, (usize, String)>,
+    ) -> Result<Self, HeightMapError> {
+        let mut width = None;
         let mut height = 0;
-        let mut heights = Vec::new();
-        heights.reserve(s.len());
-        for (row_idx, row) in s.lines().enumerate() {
-            height = row_idx + 1;
-            for (col_idx, tree_height) in row.trim().chars().enumerate() {
-                width = std::cmp::max(width, col_idx + 1);
-                let tree_height = tree_height.to_digit(10).unwrap();
-                heights.push(tree_height);
+        let mut heights = Vec::with_capacity(s.len());
+        for (line_idx, row) in s.lines().enumerate() {
+            let row = row.trim();
+            if row.is_empty() {
+                continue;
             }
+            let line = line_idx + 1;
+            let mut row = parse_row(row).map_err(|(col_idx, text)| HeightMapError::BadHeight {
+                line,
+                column: col_idx + 1,
+                text,
+            })?;
+            let expected = *width.get_or_insert(row.len());
+            if row.len() != expected {
+                return Err(HeightMapError::RaggedRow {
+                    line,
+                    width: row.len(),
+                    expected,
+                });
+            }
+            heights.append(&mut row);
+            height += 1;
         }
         Ok(TreeHeights(TreeValues {
-            width,
+            width: width.unwrap_or(0),
             height,
             values: heights,
         }))
     }
 }
 
+impl FromStr for TreeHeights {
+    type Err = HeightMapError;
+
+    /// Parses the puzzle's format: one digit per tree.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_rows(s, |row| {
+            row.chars()
+                .enumerate()
+                .map(|(col_idx, c)| c.to_digit(10).ok_or((col_idx, c.to_string())))
+                .collect()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum HeightMapError {
+    /// `line` and `column` count from 1; `column` is the position of the
+    /// height in its row, whatever separates them.
+    BadHeight {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// A row with a different number of trees from the first one.
+    RaggedRow {
+        line: usize,
+        width: usize,
+        expected: usize,
+    },
+}
+
+impl Display for HeightMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeightMapError::BadHeight { line, column, text } => {
+                write!(f, "line {line}, column {column}: bad height {text:?}")
+            }
+            HeightMapError::RaggedRow {
+                line,
+                width,
+                expected,
+            } => write!(f, "line {line}: {width} trees, expected {expected}"),
+        }
+    }
+}
+
+impl std::error::Error for HeightMapError {}
+
 #[derive(Eq, PartialEq, Debug)]
-struct TreeVisibilities(TreeValues<bool>);
+pub struct TreeVisibilities(TreeValues<bool>);
 impl TreeVisibilities {
     pub fn count(&self) -> usize {
         self.0.values.iter().copied().filter(|x| *x).count()
     }
+
+    pub fn is_visible(&self, x: usize, y: usize) -> bool {
+        *self.0.get(x, y)
+    }
 }
 
 #[test]
@@ -182,6 +415,47 @@ fn test_trees_fromstr() {
     assert_eq!(trees.height_at(1, 0), 2);
     assert_eq!(trees.height_at(0, 1), 3);
     assert_eq!(trees.height_at(1, 1), 4);
+
+    assert_eq!(
+        "12\n3x".parse::<TreeHeights>(),
+        Err(HeightMapError::BadHeight {
+            line: 2,
+            column: 2,
+            text: "x".to_string()
+        })
+    );
+    assert_eq!(
+        "123\n45\n".parse::<TreeHeights>(),
+        Err(HeightMapError::RaggedRow {
+            line: 2,
+            width: 2,
+            expected: 3
+        })
+    );
+    assert_eq!("12\n34\n\n".parse::<TreeHeights>(), Ok(trees));
+}
+
+#[test]
+fn test_parse_numbers() {
+    let expected = TreeHeights(TreeValues {
+        width: 3,
+        height: 2,
+        values: vec![1, 20, 300, 4000, 0, 6],
+    });
+    assert_eq!(
+        TreeHeights::parse_numbers("1 20 300\n4000\t0  6"),
+        Ok(expected)
+    );
+    let csv = TreeHeights::parse_numbers("1,20, 300\n4000,0,6\n").unwrap();
+    assert_eq!(csv.height_at(2, 0), 300);
+    assert_eq!(csv.visibilities().count(), 6);
+
+    let err = TreeHeights::parse_numbers("1,2\n3,,4").unwrap_err();
+    assert_eq!(err.to_string(), "line 2, column 2: bad height \"\"");
+    let err = TreeHeights::parse_numbers("1 2\n\n3 -4").unwrap_err();
+    assert_eq!(err.to_string(), "line 3, column 2: bad height \"-4\"");
+    let err = TreeHeights::parse_numbers("1 2\n3 4 5").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: 3 trees, expected 2");
 }
 
 #[test]
@@ -231,6 +505,72 @@ fn test_get_visibilities() {
     assert_eq!(heights.visibilities().count(), 21);
 }
 
+#[test]
+fn test_scenic_scores() {
+    use itertools::Itertools;
+
+    let grids = [
+        "30373\n25512\n65332\n33549\n35390",
+        "1",
+        "12345",
+        "9\n1\n9\n3\n3",
+        "271828\n182845\n904523\n536028",
+    ];
+    for grid in grids {
+        let heights: TreeHeights = grid.parse().unwrap();
+        let scores = heights.scenic_scores();
+        for (x, y) in (0..heights.width()).cartesian_product(0..heights.height()) {
+            assert_eq!(
+                *scores.get(x, y),
+                heights.scenic_score_at(x, y),
+                "{grid} at ({x}, {y})"
+            );
+        }
+    }
+    let heights: TreeHeights = grids[0].parse().unwrap();
+    assert_eq!(*heights.scenic_scores().get(2, 3), 8);
+}
+
+#[test]
+fn test_visible_from() {
+    let row: TreeHeights = "1915".parse().unwrap();
+    let seen = |eye| {
+        let visible = row.visible_from(0, 0, eye, Directions::Cardinal);
+        (0..4).map(|x| visible.is_visible(x, 0)).collect::<Vec<_>>()
+    };
+    // From the ground the 9 hides everything behind it.
+    assert_eq!(seen(1), [false, true, false, false]);
+    // From high up each tree is further down than the one before.
+    assert_eq!(seen(20), [false, true, true, true]);
+    // From a little above the 9, the 5 pokes out from behind it but the 1
+    // doesn't.
+    assert_eq!(seen(13), [false, true, false, true]);
+
+    let heights: TreeHeights = "30373\n25512\n65332\n33549\n35390".parse().unwrap();
+    let cardinal = heights.visible_from(2, 3, 5, Directions::Cardinal);
+    let visible: Vec<(usize, usize)> = (0..5)
+        .flat_map(|y| (0..5).map(move |x| (x, y)))
+        .filter(|(x, y)| cardinal.is_visible(*x, *y))
+        .collect();
+    assert_eq!(
+        visible,
+        [(2, 1), (2, 2), (0, 3), (1, 3), (3, 3), (4, 3), (2, 4)]
+    );
+
+    let all = heights.visible_from(2, 3, 5, Directions::All);
+    // Only the 2 two steps up and left is hidden, behind the 5.
+    assert_eq!(all.count(), cardinal.count() + 5);
+    assert!(all.is_visible(4, 1));
+    assert!(!all.is_visible(0, 1));
+}
+
+#[test]
+#[should_panic]
+fn test_visible_from_off_map() {
+    let row: TreeHeights = "1915".parse().unwrap();
+    row.visible_from(4, 0, 1, Directions::Cardinal);
+}
+
 pub mod p1 {
     use super::*;
 
@@ -242,17 +582,11 @@ pub mod p1 {
 }
 
 pub mod p2 {
-    use itertools::Itertools;
-
     use super::*;
 
     pub fn solve(input: &str) -> usize {
         let heights: TreeHeights = input.parse().unwrap();
-        (0..heights.width())
-            .cartesian_product(0..heights.height())
-            .map(|(x, y)| heights.scenic_score_at(x, y))
-            .max()
-            .unwrap()
+        heights.scenic_scores().values.into_iter().max().unwrap()
     }
 
     #[test]
@@ -0,0 +1,75 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    time::{Duration, Instant},
+};
+
+use day9::{visited::VisitedSet, Dir, Instruction, Pos, Rope};
+
+/// Instructions moving the head about `steps` steps in total, picked by a
+/// small linear congruential generator so every run sees the same input.
+/// `drift` is how many of every 8 moves go right or up; 4 is an unbiased
+/// random walk, anything more wanders off and covers more ground.
+fn instructions(steps: usize, drift: u64) -> Vec<Instruction> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        state >> 33
+    };
+    let mut instructions = Vec::new();
+    let mut total = 0;
+    while total < steps {
+        let r = next() % 8;
+        let dir = match (r < drift, next() % 2) {
+            (true, 0) => Dir::Right,
+            (true, _) => Dir::Up,
+            (false, 0) => Dir::Left,
+            (false, _) => Dir::Down,
+        };
+        let count = 1 + (next() % 10) as i32;
+        total += count as usize;
+        instructions.push(Instruction::Move { dir, count });
+    }
+    instructions
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+pub fn main() {
+    for (name, drift) in [("random walk", 4), ("drifting", 5)] {
+        for steps in [1_000_000, 4_000_000] {
+            let input = instructions(steps, drift);
+            // Where the tail of a 10-knot rope is before and after each step.
+            let mut rope = Rope::new(10);
+            let tail: Vec<Pos> = std::iter::once((0, 0))
+                .chain(rope.trace(&input).map(|knots| knots[9]))
+                .collect();
+
+            let (btree, btree_time) = time(|| tail.iter().copied().collect::<BTreeSet<Pos>>());
+            let (hash, hash_time) = time(|| tail.iter().copied().collect::<HashSet<Pos>>());
+            let (tiled, tiled_time) = time(|| tail.iter().copied().collect::<VisitedSet>());
+            assert_eq!(btree.len(), tiled.len());
+            assert_eq!(hash.len(), tiled.len());
+            let (rope, rope_time) = time(|| Rope::after(10, &input));
+            assert_eq!(rope.tail_visited().len(), tiled.len());
+
+            println!(
+                "{name}, {steps} steps, {} visited by the tail:",
+                tiled.len()
+            );
+            println!("  BTreeSet    {btree_time:>10.3?}");
+            println!("  HashSet     {hash_time:>10.3?}");
+            println!(
+                "  VisitedSet  {tiled_time:>10.3?} ({} tiles, {} KiB)",
+                tiled.tiles(),
+                tiled.tiles() / 2
+            );
+            println!("  whole rope  {rope_time:>10.3?}");
+        }
+    }
+}
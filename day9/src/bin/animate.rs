@@ -0,0 +1,65 @@
+use std::{fs::File, io::BufWriter, io::Read, path::Path};
+
+use day9::{
+    parse_instructions,
+    render::{self, Every},
+    Rope,
+};
+
+/// Reads instructions from stdin and prints the rope after every step,
+/// followed by a map of where the tail went.
+///
+/// Arguments are all optional: the number of knots (default 10), `step` or
+/// `instruction` for how often to draw, and a directory to write numbered
+/// PPM frames into instead, with an optional number of pixels per position.
+pub fn main() {
+    let mut args = std::env::args().skip(1);
+    let len = args.next().map_or(10, |l| l.parse().unwrap());
+    let every = match args.next().as_deref() {
+        None | Some("step") => Every::Step,
+        Some("instruction") => Every::Instruction,
+        Some(other) => panic!("Unknown frequency {other}"),
+    };
+    let ppm_dir = args.next();
+    let scale = args.next().map_or(4, |s| s.parse().unwrap());
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let instructions = parse_instructions(&input);
+
+    if let Some(dir) = ppm_dir {
+        let viewport = render::bounds(len, &instructions);
+        let mut rope = Rope::new(len);
+        let mut frame = 0;
+        let mut write_frame = |rope: &Rope| {
+            let path = Path::new(&dir).join(format!("frame{frame:06}.ppm"));
+            let mut out = BufWriter::new(File::create(path).unwrap());
+            render::write_ppm(rope, &viewport, scale, &mut out).unwrap();
+            frame += 1;
+        };
+        write_frame(&rope);
+        for instruction in &instructions {
+            match every {
+                Every::Step => {
+                    for offset in instruction.head_moves() {
+                        rope.move_head(offset);
+                        write_frame(&rope);
+                    }
+                }
+                Every::Instruction => {
+                    rope.apply(*instruction);
+                    write_frame(&rope);
+                }
+            }
+        }
+        return;
+    }
+
+    let frames = render::frames(len, &instructions, every, Default::default());
+    for frame in &frames {
+        println!("{frame}");
+    }
+    let rope = Rope::after(len, &instructions);
+    let mut viewport = render::bounds(len, &instructions);
+    rope.tail_visited().iter().for_each(|p| viewport.include(p));
+    print!("{}", render::render_visited(rope.tail_visited(), &viewport));
+}
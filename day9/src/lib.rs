@@ -1,11 +1,39 @@
 use std::str::FromStr;
 
+pub mod render;
+pub mod visited;
+
+use visited::VisitedSet;
+
+/// `(x, y)`, with `y` increasing upwards.
+pub type Pos = (i32, i32);
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Dir {
+pub enum Dir {
     Up,
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Dir {
+    /// How far one step moves.
+    pub fn offset(&self) -> Pos {
+        match self {
+            Dir::Up => (0, 1),
+            Dir::Down => (0, -1),
+            Dir::Left => (-1, 0),
+            Dir::Right => (1, 0),
+            Dir::UpLeft => (-1, 1),
+            Dir::UpRight => (1, 1),
+            Dir::DownLeft => (-1, -1),
+            Dir::DownRight => (1, -1),
+        }
+    }
 }
 
 impl FromStr for Dir {
@@ -18,36 +46,86 @@ impl FromStr for Dir {
             "D" => Down,
             "L" => Left,
             "R" => Right,
+            "UL" => UpLeft,
+            "UR" => UpRight,
+            "DL" => DownLeft,
+            "DR" => DownRight,
             _ => panic!("Unrecognized dir {s}"),
         })
     }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-struct Instruction {
-    dir: Dir,
-    count: i32,
+pub enum Instruction {
+    /// `count` steps in `dir`, e.g. `R 4` or `UL 2`.
+    Move { dir: Dir, count: i32 },
+    /// Straight to `dx` across and `dy` up from where the head is, in one
+    /// go, e.g. `J 5 -3`.
+    Jump { dx: i32, dy: i32 },
+}
+
+impl Instruction {
+    /// How the head moves, one offset per step.
+    pub fn head_moves(&self) -> impl Iterator<Item = Pos> {
+        let (offset, steps) = match *self {
+            Instruction::Move { dir, count } => (dir.offset(), count.max(0) as usize),
+            Instruction::Jump { dx, dy } => ((dx, dy), 1),
+        };
+        std::iter::repeat(offset).take(steps)
+    }
 }
 
 impl FromStr for Instruction {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (dir, count) = s.split_once(' ').unwrap();
-        Ok(Instruction {
+        let (dir, rest) = s.split_once(' ').unwrap();
+        if dir == "J" {
+            let (dx, dy) = rest.trim().split_once(' ').unwrap();
+            return Ok(Instruction::Jump {
+                dx: dx.parse().unwrap(),
+                dy: dy.trim().parse().unwrap(),
+            });
+        }
+        Ok(Instruction::Move {
             dir: dir.parse().unwrap(),
-            count: count.parse().unwrap(),
+            count: rest.parse().unwrap(),
         })
     }
 }
 
+#[test]
+fn test_parse_instruction() {
+    assert_eq!(
+        "R 4".parse(),
+        Ok(Instruction::Move {
+            dir: Dir::Right,
+            count: 4
+        })
+    );
+    assert_eq!(
+        "DL 2".parse(),
+        Ok(Instruction::Move {
+            dir: Dir::DownLeft,
+            count: 2
+        })
+    );
+    assert_eq!("J 5 -3".parse(), Ok(Instruction::Jump { dx: 5, dy: -3 }));
+    let moves: Vec<Pos> = "UR 3"
+        .parse::<Instruction>()
+        .unwrap()
+        .head_moves()
+        .collect();
+    assert_eq!(moves, [(1, 1); 3]);
+}
+
+pub fn parse_instructions(input: &str) -> Vec<Instruction> {
+    input.lines().map(|l| l.trim().parse().unwrap()).collect()
+}
+
 fn head_pos(prev: (i32, i32), dir: Dir) -> (i32, i32) {
-    match dir {
-        Dir::Up => (prev.0, prev.1 + 1),
-        Dir::Down => (prev.0, prev.1 - 1),
-        Dir::Left => (prev.0 - 1, prev.1),
-        Dir::Right => (prev.0 + 1, prev.1),
-    }
+    let (dx, dy) = dir.offset();
+    (prev.0 + dx, prev.1 + dy)
 }
 #[test]
 fn test_head_pos() {
@@ -55,14 +133,16 @@ fn test_head_pos() {
     assert_eq!(head_pos((0, 0), Dir::Right), (1, 0));
     assert_eq!(head_pos((0, 0), Dir::Up), (0, 1));
     assert_eq!(head_pos((0, 0), Dir::Down), (0, -1));
+    assert_eq!(head_pos((0, 0), Dir::UpLeft), (-1, 1));
+    assert_eq!(head_pos((0, 0), Dir::DownRight), (1, -1));
 }
 
+/// Where `tail` goes to follow `head`: one step towards it, or nowhere if
+/// they're already touching.
 fn tail_pos(tail: (i32, i32), head: (i32, i32)) -> (i32, i32) {
     let diff = (head.0 - tail.0, head.1 - tail.1);
     let diff_sign = (diff.0.signum(), diff.1.signum());
     let diff_mag = (diff.0.abs(), diff.1.abs());
-    assert!(diff_mag.0 <= 2);
-    assert!(diff_mag.1 <= 2);
     if diff_mag.0 < 2 && diff_mag.1 < 2 {
         (tail.0, tail.1)
     } else {
@@ -77,28 +157,159 @@ fn test_tail_pos() {
     assert_eq!(tail_pos((0, 0), (1, 1)), (0, 0));
     assert_eq!(tail_pos((0, 0), (2, 2)), (1, 1));
     assert_eq!(tail_pos((0, 0), (1, 2)), (1, 1));
+    assert_eq!(tail_pos((0, 0), (5, -2)), (1, -1));
 }
 
-pub mod p1 {
-    use std::collections::BTreeSet;
+/// A rope of knots that all start at the origin. Knot 0 is the head, and
+/// every other knot follows the one before it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rope {
+    knots: Vec<Pos>,
+    /// Every position each knot has been in, indexed like `knots`.
+    visited: Vec<VisitedSet>,
+}
 
-    use super::*;
-    pub fn solve(input: &str) -> usize {
-        let instructions = input
-            .lines()
-            .map(|l| -> Instruction { l.trim().parse().unwrap() });
-        let mut head = (0i32, 0i32);
-        let mut tail = (0i32, 0i32);
-        let mut positions = BTreeSet::<(i32, i32)>::new();
-        positions.insert(tail);
-        for i in instructions {
-            for _ in 0..i.count {
-                head = head_pos(head, i.dir);
-                tail = tail_pos(tail, head);
-                positions.insert(tail);
+impl Rope {
+    pub fn new(len: usize) -> Self {
+        assert!(len > 0, "A rope needs at least a head");
+        Rope {
+            knots: vec![(0, 0); len],
+            visited: vec![VisitedSet::from_iter([(0, 0)]); len],
+        }
+    }
+
+    /// A rope of `len` knots after following all of `instructions`.
+    pub fn after(len: usize, instructions: &[Instruction]) -> Self {
+        let mut rope = Rope::new(len);
+        for instruction in instructions {
+            rope.apply(*instruction);
+        }
+        rope
+    }
+
+    pub fn knots(&self) -> &[Pos] {
+        &self.knots
+    }
+
+    pub fn head(&self) -> Pos {
+        self.knots[0]
+    }
+
+    pub fn tail(&self) -> Pos {
+        *self.knots.last().unwrap()
+    }
+
+    pub fn visited(&self, knot: usize) -> &VisitedSet {
+        &self.visited[knot]
+    }
+
+    pub fn tail_visited(&self) -> &VisitedSet {
+        self.visited.last().unwrap()
+    }
+
+    /// Moves the head one step and lets the rest of the rope catch up,
+    /// returning where every knot ends up.
+    pub fn step(&mut self, dir: Dir) -> &[Pos] {
+        self.knots[0] = head_pos(self.knots[0], dir);
+        self.catch_up()
+    }
+
+    /// Moves the head by `offset`, however far that is, and lets the rest of
+    /// the rope catch up.
+    pub fn move_head(&mut self, offset: Pos) -> &[Pos] {
+        self.knots[0] = (self.knots[0].0 + offset.0, self.knots[0].1 + offset.1);
+        self.catch_up()
+    }
+
+    /// Knots only ever move one step at a time, so after a long jump they
+    /// keep taking steps, each following the knot before it, until every
+    /// knot is touching the next.
+    fn catch_up(&mut self) -> &[Pos] {
+        self.visited[0].insert(self.knots[0]);
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for i in 1..self.knots.len() {
+                let next = tail_pos(self.knots[i], self.knots[i - 1]);
+                if next != self.knots[i] {
+                    self.knots[i] = next;
+                    self.visited[i].insert(next);
+                    moved = true;
+                }
             }
         }
-        positions.len()
+        &self.knots
+    }
+
+    pub fn apply(&mut self, instruction: Instruction) {
+        for offset in instruction.head_moves() {
+            self.move_head(offset);
+        }
+    }
+
+    /// Follows `instructions` one step (or jump) at a time, yielding where
+    /// every knot is after each one.
+    pub fn trace<'a>(
+        &'a mut self,
+        instructions: &'a [Instruction],
+    ) -> impl Iterator<Item = Vec<Pos>> + 'a {
+        instructions
+            .iter()
+            .flat_map(|i| i.head_moves())
+            .map(move |offset| self.move_head(offset).to_vec())
+    }
+}
+
+#[test]
+fn test_rope() {
+    let instructions = parse_instructions("R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2");
+    let rope = Rope::after(10, &instructions);
+    assert_eq!(rope.head(), (2, 2));
+    assert_eq!(rope.visited(1).len(), 13);
+    assert_eq!(rope.tail_visited().len(), 1);
+    assert_eq!(
+        rope.visited(0).len(),
+        Rope::after(1, &instructions).tail_visited().len()
+    );
+
+    let mut rope = Rope::new(3);
+    let steps: Vec<Vec<Pos>> = rope.trace(&instructions[..2]).collect();
+    assert_eq!(steps.len(), 8);
+    assert_eq!(steps[0], [(1, 0), (0, 0), (0, 0)]);
+    assert_eq!(steps[2], [(3, 0), (2, 0), (1, 0)]);
+    assert_eq!(steps[5], [(4, 2), (4, 1), (3, 1)]);
+    assert_eq!(rope.knots(), steps[7]);
+    assert_eq!(rope.tail(), (4, 2));
+}
+
+#[test]
+fn test_diagonals_and_jumps() {
+    let mut rope = Rope::new(3);
+    rope.apply("UR 2".parse().unwrap());
+    assert_eq!(rope.knots(), [(2, 2), (1, 1), (0, 0)]);
+
+    // The knots chase the head across the gap, leaving a trail behind.
+    rope.apply("J 4 0".parse().unwrap());
+    assert_eq!(rope.knots(), [(6, 2), (5, 2), (4, 2)]);
+    let mut trail: Vec<Pos> = rope.visited(1).iter().collect();
+    trail.sort();
+    assert_eq!(trail, [(0, 0), (1, 1), (2, 2), (3, 2), (4, 2), (5, 2)]);
+    assert_eq!(rope.tail_visited().len(), 5);
+    assert_eq!(rope.visited(0).len(), 4);
+
+    // A jump of one step is the same as a move.
+    let moves = parse_instructions("R 4\nU 4\nDL 3\nL 2");
+    let jumps = parse_instructions("J 1 0\nJ 1 0\nJ 1 0\nJ 1 0\nJ 0 1\nJ 0 1\nJ 0 1\nJ 0 1\nJ -1 -1\nJ -1 -1\nJ -1 -1\nJ -1 0\nJ -1 0");
+    assert_eq!(Rope::after(10, &moves), Rope::after(10, &jumps));
+}
+
+pub mod p1 {
+    use super::*;
+
+    pub fn solve(input: &str) -> usize {
+        Rope::after(2, &parse_instructions(input))
+            .tail_visited()
+            .len()
     }
 
     #[test]
@@ -116,26 +327,12 @@ pub mod p1 {
 }
 
 pub mod p2 {
-    use std::collections::BTreeSet;
-
     use super::*;
+
     pub fn solve(input: &str) -> usize {
-        let instructions = input
-            .lines()
-            .map(|l| -> Instruction { l.trim().parse().unwrap() });
-        let mut knots = [(0i32, 0i32); 10];
-        let mut positions = BTreeSet::<(i32, i32)>::new();
-        positions.insert(knots[9]);
-        for i in instructions {
-            for _ in 0..i.count {
-                knots[0] = head_pos(knots[0], i.dir);
-                for i in 1..knots.len() {
-                    knots[i] = tail_pos(knots[i], knots[i-1]);
-                }
-                positions.insert(knots[9]);
-            }
-        }
-        positions.len()
+        Rope::after(10, &parse_instructions(input))
+            .tail_visited()
+            .len()
     }
 
     #[test]
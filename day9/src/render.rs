@@ -0,0 +1,232 @@
+//! Drawing ropes the way the puzzle statement does, as text or as PPM
+//! images.
+
+use std::io::{self, Write};
+
+use crate::{visited::VisitedSet, Instruction, Pos, Rope};
+
+/// The part of the plane being drawn, inclusive at both corners.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Viewport {
+    pub min: Pos,
+    pub max: Pos,
+}
+
+impl Viewport {
+    pub fn new(min: Pos, max: Pos) -> Self {
+        Viewport { min, max }
+    }
+
+    /// Grows to take in `pos`, if it's not already in view.
+    pub fn include(&mut self, pos: Pos) {
+        self.min = (self.min.0.min(pos.0), self.min.1.min(pos.1));
+        self.max = (self.max.0.max(pos.0), self.max.1.max(pos.1));
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.0 - self.min.0 + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.1 - self.min.1 + 1) as usize
+    }
+
+    /// Every position in view, a row at a time from the top.
+    fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = Pos>> {
+        let (min, max) = (self.min, self.max);
+        (min.1..=max.1)
+            .rev()
+            .map(move |y| (min.0..=max.0).map(move |x| (x, y)))
+    }
+}
+
+impl Default for Viewport {
+    /// Just the starting point.
+    fn default() -> Self {
+        Viewport::new((0, 0), (0, 0))
+    }
+}
+
+/// How knot `i` of a rope of `len` knots is drawn: `H` for the head, then
+/// `T` for the tail of a two-knot rope or the knot's number otherwise, and
+/// `+` for knots past 9.
+pub fn knot_label(len: usize, i: usize) -> char {
+    match i {
+        0 => 'H',
+        1 if len == 2 => 'T',
+        1..=9 => char::from_digit(i as u32, 10).unwrap(),
+        _ => '+',
+    }
+}
+
+/// The knots in puzzle notation, with `s` at the start if nothing covers
+/// it. Where knots overlap the one nearest the head is drawn.
+pub fn render_rope(knots: &[Pos], viewport: &Viewport) -> String {
+    let mut out = String::new();
+    for row in viewport.rows() {
+        for pos in row {
+            let c = match knots.iter().position(|k| *k == pos) {
+                Some(i) => knot_label(knots.len(), i),
+                None if pos == (0, 0) => 's',
+                None => '.',
+            };
+            out.push(c);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `#` for every visited position, except the start which is `s`.
+pub fn render_visited(visited: &VisitedSet, viewport: &Viewport) -> String {
+    let mut out = String::new();
+    for row in viewport.rows() {
+        for pos in row {
+            let c = if pos == (0, 0) {
+                's'
+            } else if visited.contains(&pos) {
+                '#'
+            } else {
+                '.'
+            };
+            out.push(c);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Every {
+    Step,
+    Instruction,
+}
+
+/// Text frames of a rope of `len` knots following `instructions`, starting
+/// with where it begins. The view starts as `viewport` and grows whenever a
+/// knot would leave it.
+pub fn frames(
+    len: usize,
+    instructions: &[Instruction],
+    every: Every,
+    mut viewport: Viewport,
+) -> Vec<String> {
+    let mut rope = Rope::new(len);
+    let mut frames = vec![render_rope(rope.knots(), &viewport)];
+    let mut draw = |knots: &[Pos], frames: &mut Vec<String>| {
+        knots.iter().for_each(|k| viewport.include(*k));
+        frames.push(render_rope(knots, &viewport));
+    };
+    for instruction in instructions {
+        match every {
+            Every::Step => {
+                for offset in instruction.head_moves() {
+                    draw(rope.move_head(offset), &mut frames);
+                }
+            }
+            Every::Instruction => {
+                rope.apply(*instruction);
+                draw(rope.knots(), &mut frames);
+            }
+        }
+    }
+    frames
+}
+
+/// The smallest view that holds every knot of a rope of `len` knots at
+/// every step of `instructions`, so frames can all be the same size.
+pub fn bounds(len: usize, instructions: &[Instruction]) -> Viewport {
+    let mut viewport = Viewport::default();
+    let mut rope = Rope::new(len);
+    for knots in rope.trace(instructions) {
+        knots.iter().for_each(|k| viewport.include(*k));
+    }
+    viewport
+}
+
+/// Writes the rope as a binary PPM with `scale` pixels per position: the
+/// tail's trail in gray, the start in green, and the knots shading from red
+/// at the head to blue at the tail.
+pub fn write_ppm(
+    rope: &Rope,
+    viewport: &Viewport,
+    scale: usize,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let knots = rope.knots();
+    write!(
+        out,
+        "P6\n{} {}\n255\n",
+        viewport.width() * scale,
+        viewport.height() * scale
+    )?;
+    for row in viewport.rows() {
+        let row: Vec<[u8; 3]> = row
+            .map(|pos| match knots.iter().position(|k| *k == pos) {
+                Some(i) => {
+                    let t = if knots.len() > 1 {
+                        i * 255 / (knots.len() - 1)
+                    } else {
+                        0
+                    };
+                    [255 - t as u8, 0, t as u8]
+                }
+                None if pos == (0, 0) => [0, 160, 0],
+                None if rope.tail_visited().contains(&pos) => [96, 96, 96],
+                None => [0, 0, 0],
+            })
+            .collect();
+        for _ in 0..scale {
+            for pixel in &row {
+                for _ in 0..scale {
+                    out.write_all(pixel)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2";
+
+#[test]
+fn test_render_rope() {
+    let viewport = Viewport::new((0, 0), (5, 4));
+    let instructions = crate::parse_instructions(EXAMPLE);
+    let frames = frames(2, &instructions, Every::Step, viewport);
+    assert_eq!(frames.len(), 25);
+    assert_eq!(frames[0], "......\n......\n......\n......\nH.....\n");
+    assert_eq!(frames[1], "......\n......\n......\n......\nTH....\n");
+    assert_eq!(frames[2], "......\n......\n......\n......\nsTH...\n");
+    assert_eq!(frames[6], "......\n......\n....H.\n....T.\ns.....\n");
+    assert_eq!(frames[24], "......\n......\n.TH...\n......\ns.....\n");
+
+    let per_instruction = crate::render::frames(2, &instructions, Every::Instruction, viewport);
+    assert_eq!(per_instruction.len(), 9);
+    assert_eq!(per_instruction[2], frames[8]);
+    assert_eq!(per_instruction[8], frames[24]);
+
+    let rope = Rope::after(2, &instructions);
+    assert_eq!(
+        render_visited(rope.tail_visited(), &viewport),
+        "..##..\n...##.\n.####.\n....#.\ns###..\n"
+    );
+}
+
+#[test]
+fn test_viewport_grows() {
+    let instructions = crate::parse_instructions("L 2\nU 1");
+    let frames = frames(10, &instructions, Every::Step, Viewport::default());
+    assert_eq!(frames, ["H\n", "H1\n", "H12\n", "H..\n.12\n"]);
+    assert_eq!(bounds(10, &instructions), Viewport::new((-2, 0), (0, 1)));
+    assert_eq!(knot_label(12, 11), '+');
+}
+
+#[test]
+fn test_write_ppm() {
+    let rope = Rope::after(2, &crate::parse_instructions("R 2"));
+    let mut ppm = Vec::new();
+    write_ppm(&rope, &Viewport::new((0, 0), (2, 0)), 1, &mut ppm).unwrap();
+    assert_eq!(ppm, b"P6\n3 1\n255\n\x00\xa0\x00\x00\x00\xff\xff\x00\x00");
+}
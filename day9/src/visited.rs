@@ -0,0 +1,119 @@
+//! A set of grid positions stored as bitmaps of 64 by 64 tiles. Each tile a
+//! rope passes through takes 512 bytes however few of its positions are
+//! visited, so a position costs a bit at best, when its tile is full, and
+//! tens of bits on a path that drifts across the plane.
+
+use std::collections::HashMap;
+
+use crate::Pos;
+
+const TILE_SHIFT: i32 = 6;
+const TILE_MASK: i32 = (1 << TILE_SHIFT) - 1;
+
+/// One `u64` per row of a tile, bit `x` set if column `x` is in the set.
+type Tile = [u64; 1 << TILE_SHIFT];
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct VisitedSet {
+    tiles: HashMap<Pos, Box<Tile>>,
+    len: usize,
+}
+
+/// Which tile `pos` is in, and its row and column within it. Arithmetic
+/// shifts round down, so negative positions land in the right tile.
+fn locate(pos: Pos) -> (Pos, usize, u32) {
+    let tile = (pos.0 >> TILE_SHIFT, pos.1 >> TILE_SHIFT);
+    (
+        tile,
+        (pos.1 & TILE_MASK) as usize,
+        (pos.0 & TILE_MASK) as u32,
+    )
+}
+
+impl VisitedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether `pos` wasn't already in the set.
+    pub fn insert(&mut self, pos: Pos) -> bool {
+        let (tile, row, col) = locate(pos);
+        let rows = self
+            .tiles
+            .entry(tile)
+            .or_insert_with(|| Box::new([0; 1 << TILE_SHIFT]));
+        let bit = 1 << col;
+        if rows[row] & bit != 0 {
+            return false;
+        }
+        rows[row] |= bit;
+        self.len += 1;
+        true
+    }
+
+    pub fn contains(&self, pos: &Pos) -> bool {
+        let (tile, row, col) = locate(*pos);
+        self.tiles
+            .get(&tile)
+            .map_or(false, |rows| rows[row] & (1 << col) != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of tiles allocated, each taking 512 bytes.
+    pub fn tiles(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Every position in the set, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.tiles.iter().flat_map(|(tile, rows)| {
+            let origin = (tile.0 << TILE_SHIFT, tile.1 << TILE_SHIFT);
+            rows.iter().enumerate().flat_map(move |(row, bits)| {
+                (0..64)
+                    .filter(move |col| bits & (1 << col) != 0)
+                    .map(move |col| (origin.0 + col, origin.1 + row as i32))
+            })
+        })
+    }
+}
+
+impl FromIterator<Pos> for VisitedSet {
+    fn from_iter<I: IntoIterator<Item = Pos>>(iter: I) -> Self {
+        let mut set = VisitedSet::new();
+        for pos in iter {
+            set.insert(pos);
+        }
+        set
+    }
+}
+
+#[test]
+fn test_visited_set() {
+    let mut set = VisitedSet::new();
+    assert!(set.is_empty());
+    let positions = [(0, 0), (63, 63), (64, 0), (-1, -1), (-64, 5), (-65, -200)];
+    for pos in positions {
+        assert!(set.insert(pos));
+        assert!(set.contains(&pos));
+    }
+    assert!(!set.insert((63, 63)));
+    assert_eq!(set.len(), positions.len());
+    assert_eq!(set.tiles(), 5);
+    assert!(!set.contains(&(1, 0)));
+    assert!(!set.contains(&(0, -1)));
+    assert!(!set.contains(&(1000, 1000)));
+
+    let mut all: Vec<Pos> = set.iter().collect();
+    all.sort();
+    let mut expected = positions.to_vec();
+    expected.sort();
+    assert_eq!(all, expected);
+    assert_eq!(positions.into_iter().collect::<VisitedSet>(), set);
+}